@@ -0,0 +1,228 @@
+//! Writing homework snapshots as tar or zip archives, and unpacking
+//! archives for upload.
+
+use super::errors::*;
+use super::messages::DateTime;
+
+use chrono::{Datelike, Local, Timelike};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+/// An archive being written to `W`.
+pub enum Archive<W: Write> {
+    Tar(tar::Builder<W>),
+    TarGz(tar::Builder<GzEncoder<W>>),
+    // Zip needs to seek, so it’s built in memory and copied out at the end.
+    Zip(zip::ZipWriter<Cursor<Vec<u8>>>, W),
+}
+
+impl ArchiveFormat {
+    /// Guesses the format from a file name’s extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+
+        if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tar"            => Ok(ArchiveFormat::Tar),
+            "tar.gz" | "tgz" => Ok(ArchiveFormat::TarGz),
+            "zip"            => Ok(ArchiveFormat::Zip),
+            _                => Err(syntax_error("archive type", s))?,
+        }
+    }
+}
+
+impl<W: Write> Archive<W> {
+    pub fn new(format: ArchiveFormat, out: W) -> Self {
+        match format {
+            ArchiveFormat::Tar   => Archive::Tar(tar::Builder::new(out)),
+            ArchiveFormat::TarGz => Archive::TarGz(tar::Builder::new(
+                GzEncoder::new(out, flate2::Compression::default()))),
+            ArchiveFormat::Zip   => Archive::Zip(zip::ZipWriter::new(Cursor::new(Vec::new())), out),
+        }
+    }
+
+    /// Adds a file at `path` within the archive, last modified at `mtime`.
+    pub fn append(&mut self, path: &str, mtime: &DateTime, contents: &[u8]) -> Result<()> {
+        match self {
+            Archive::Tar(builder)   => append_tar(builder, path, mtime, contents),
+            Archive::TarGz(builder) => append_tar(builder, path, mtime, contents),
+            Archive::Zip(writer, _) => {
+                let options = zip::write::FileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated)
+                    .last_modified_time(zip_time(mtime))
+                    .unix_permissions(0o644);
+                writer.start_file(path, options)?;
+                writer.write_all(contents)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the end of the archive.
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Archive::Tar(builder)             => {
+                builder.into_inner()?.flush()?;
+            }
+            Archive::TarGz(builder)           => {
+                builder.into_inner()?.finish()?.flush()?;
+            }
+            Archive::Zip(mut writer, mut out) => {
+                let buffer = writer.finish()?.into_inner();
+                out.write_all(&buffer)?;
+                out.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn append_tar<W: Write>(builder: &mut tar::Builder<W>,
+                        path: &str,
+                        mtime: &DateTime,
+                        contents: &[u8])
+    -> Result<()> {
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime.to_utc().timestamp().max(0) as u64);
+    builder.append_data(&mut header, path, contents)?;
+    Ok(())
+}
+
+// Zip stores local time, and only from 1980 on.
+fn zip_time(mtime: &DateTime) -> zip::DateTime {
+    let local = mtime.to_utc().with_timezone(&Local);
+
+    zip::DateTime::from_date_and_time(local.year() as u16, local.month() as u8, local.day() as u8,
+                                      local.hour() as u8, local.minute() as u8,
+                                      local.second() as u8)
+        .unwrap_or_default()
+}
+
+/// Unpacks the regular files in the archive at `src` into the directory
+/// `dst`, dropping the first `strip` components of each member’s path.
+/// Members that can’t be unpacked safely, such as links and paths that
+/// would escape `dst`, are passed to `skip` with a reason.
+pub fn extract<F>(src: &Path, format: ArchiveFormat, dst: &Path, strip: usize, mut skip: F)
+    -> Result<()>
+    where F: FnMut(String) {
+
+    let file = fs::File::open(src)
+        .chain_err(|| format!("Could not open archive ‘{}’", src.display()))?;
+
+    match format {
+        ArchiveFormat::Tar   => extract_tar(tar::Archive::new(file), dst, strip, &mut skip),
+        ArchiveFormat::TarGz => extract_tar(tar::Archive::new(GzDecoder::new(file)),
+                                            dst, strip, &mut skip),
+        ArchiveFormat::Zip   => extract_zip(zip::ZipArchive::new(file)?, dst, strip, &mut skip),
+    }.chain_err(|| format!("Could not unpack archive ‘{}’", src.display()))
+}
+
+fn extract_tar<R: Read>(mut archive: tar::Archive<R>,
+                        dst: &Path,
+                        strip: usize,
+                        skip: &mut dyn FnMut(String))
+    -> Result<()> {
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path      = entry.path()?.into_owned();
+        let kind      = entry.header().entry_type();
+
+        if kind.is_dir() {
+            continue;
+        } else if !kind.is_file() {
+            skip(format!("Skipping ‘{}’, which is not a regular file.", path.display()));
+        } else if let Some(rel) = member_path(&path, strip, skip) {
+            write_member(dst, &rel, &mut entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_zip<R: Read + io::Seek>(mut archive: zip::ZipArchive<R>,
+                                   dst: &Path,
+                                   strip: usize,
+                                   skip: &mut dyn FnMut(String))
+    -> Result<()> {
+
+    for i in 0 .. archive.len() {
+        let mut file = archive.by_index(i)?;
+        let path     = PathBuf::from(file.name());
+
+        if file.is_dir() {
+            continue;
+        } else if file.unix_mode().is_some_and(|mode| mode & 0o170000 == 0o120000) {
+            skip(format!("Skipping symbolic link ‘{}’.", path.display()));
+        } else if let Some(rel) = member_path(&path, strip, skip) {
+            write_member(dst, &rel, &mut file)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Where to unpack the member at `path`, if anywhere.
+fn member_path(path: &Path, strip: usize, skip: &mut dyn FnMut(String)) -> Option<PathBuf> {
+    let mut components = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => components.push(part),
+            Component::CurDir       => (),
+            _                       => {
+                skip(format!("Skipping ‘{}’, which is outside the archive.", path.display()));
+                return None;
+            }
+        }
+    }
+
+    if components.len() <= strip {
+        return None;
+    }
+
+    Some(components[strip ..].iter().collect())
+}
+
+fn write_member<R: Read>(dst: &Path, rel: &Path, contents: &mut R) -> Result<()> {
+    let path = dst.join(rel);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = fs::File::create(&path)?;
+    io::copy(contents, &mut file)?;
+    Ok(())
+}
@@ -1,9 +1,12 @@
 use gsc_client::*;
 use gsc_client::errors::{Result, ResultExt, ErrorKind, syntax_error};
+use gsc_client::spec::{CpSpec, RemoteSpec, parse_cp_arg, parse_hw, parse_hw_file,
+                       parse_hw_opt_file};
 
 use vlog::*;
 
 use std::error::Error;
+use std::path::PathBuf;
 use std::process::exit;
 use std::str::FromStr;
 
@@ -12,19 +15,19 @@ fn main() {
 
     match do_it() {
         Err(err)  => {
-            ve1!("{}", err);
+            ve1!("{}", style::paint_err(style::Style::Error, &err));
 
             let mut source = err.source();
 
             while let Some(error) = source {
-                ve1!("Source: {}", error);
+                ve1!("{}", style::paint_err(style::Style::Error,
+                                            format!("Source: {}", error)));
                 source = error.source();
             }
 
             exit(1);
         }
-        Ok(true)  => exit(2),
-        Ok(false) => (),
+        Ok(status) => exit(status),
     }
 }
 
@@ -32,39 +35,57 @@ enum Command {
     AdminCsv,
     AdminDivorce{user: String, hw: usize},
     AdminExtend{user: String, hw: usize, date: String, eval: bool},
+    AdminGradebook{policy: Option<PathBuf>, filter: GradebookFilter},
     AdminPartners{user: String, hw: usize},
     AdminSetGrade{user: String, hw: usize, number: usize, score: f64, comment: String},
     AdminSetAuto{user: String, hw: usize, score: f64, comment: String},
     AdminSetExam{user: String, exam: usize, num: usize, den: usize},
     AdminSubmissions{hw: usize},
+    Archive{hw: usize, dst: PathBuf, format: archive::ArchiveFormat},
     Auth{user: String},
-    Cat{rpats: Vec<RemotePattern>},
+    CacheClear,
+    Cat{rspecs: Vec<RemoteSpec>},
+    Check{hw: usize, manifest: Option<PathBuf>},
     Create{user: String},
-    Cp{srcs: Vec<CpArg>, dst: CpArg},
+    Cp{srcs: Vec<CpSpec>, dst: CpSpec},
+    Deadlines{ics: Option<PathBuf>},
     Deauth,
-    Ls{rpats: Vec<RemotePattern>},
+    Grep{pattern: String, rspecs: Vec<RemoteSpec>, options: GrepOptions},
+    Ls{rspecs: Vec<RemoteSpec>, options: LsOptions},
+    Mv{src: RemotePattern, name: String},
     Partner,
     PartnerRequest{hw: usize, them: String},
     PartnerAccept{hw: usize, them: String},
     PartnerCancel{hw: usize, them: String},
     Passwd,
-    Rm{rpats: Vec<RemotePattern>},
+    Restore{rspecs: Vec<RemoteSpec>},
+    Rm{rspecs: Vec<RemoteSpec>},
     Status{hw: Option<usize>},
+    Submit{hw: usize, dir: PathBuf},
+    Verify{hw: usize, dir: PathBuf},
     Whoami,
 }
 
-fn do_it() -> Result<bool> {
+// Runs the command, returning the exit status: 2 if there were warnings,
+// 1 if a search found nothing, and 0 otherwise.
+fn do_it() -> Result<i32> {
     let mut client = GscClient::new()?;
     let command    = GscClientApp::new().process(client.config_mut())?;
     client.config().activate_verbosity();
+    client.config().activate_date_display();
+    client.config().activate_color();
 
     use self::Command::*;
 
+    let mut found = true;
+
     match command {
         AdminCsv                     => client.admin_csv(),
         AdminDivorce{user, hw}       => client.admin_divorce(&user, hw),
         AdminExtend{user, hw, date, eval}
                                      => client.admin_extend(&user, hw, &date, eval),
+        AdminGradebook{policy, filter}
+                                     => client.admin_gradebook(policy.as_deref(), &filter),
         AdminPartners{user, hw}      => client.admin_partners(&user, hw),
         AdminSetGrade{user, hw, number, score, comment}
                                      => client.admin_set_grade(&user, hw, number,
@@ -74,28 +95,74 @@ fn do_it() -> Result<bool> {
         AdminSetExam{user, exam, num, den}
                                      => client.admin_set_exam(&user, exam, num, den),
         AdminSubmissions{hw}         => client.admin_submissions(hw),
+        Archive{hw, dst, format}     => client.archive(hw, &dst, format),
         Auth{user}                   => client.auth(&user),
-        Cat{rpats}                   => client.cat(&rpats),
+        CacheClear                   => client.cache_clear(),
+        Cat{rspecs}                  => client.cat(&client.expand_specs(&rspecs)?),
+        Check{hw, manifest}          => client.check(hw, manifest.as_deref()),
         Create{user}                 => client.create(&user),
-        Cp{srcs, dst}                => client.cp(&srcs, &dst),
+        Cp{srcs, dst}                => client.cp(&client.expand_cp_specs(&srcs)?,
+                                                  &client.expand_cp_dst(&dst)?),
+        Deadlines{ics}               => client.deadlines(ics.as_deref()),
         Deauth                       => client.deauth(),
-        Ls{rpats}                    => client.ls(&rpats),
+        Grep{pattern, rspecs, options}
+                                     => client.grep(&pattern, &client.expand_specs(&rspecs)?,
+                                                    &options)
+                                            .map(|matched| found = matched),
+        Ls{rspecs, options}          => client.ls(&client.expand_specs(&rspecs)?, &options),
+        Mv{src, name}                => client.mv(&src, &name),
         Partner                      => client.partner(),
         PartnerRequest{hw, them}     => client.partner_request(hw, &them),
         PartnerAccept{hw, them}      => client.partner_accept(hw, &them),
         PartnerCancel{hw, them}      => client.partner_cancel(hw, &them),
         Passwd                       => client.passwd(),
-        Rm{rpats}                    => client.rm(&rpats),
+        Restore{rspecs}              => client.restore(&rspecs),
+        Rm{rspecs}                   => client.rm(&client.expand_specs(&rspecs)?),
         Status{hw: Some(i)}          => client.status_hw(i),
         Status{hw: None}             => client.status_user(),
+        Submit{hw, dir}              => client.submit(hw, &dir),
+        Verify{hw, dir}              => client.verify(hw, &dir),
         Whoami                       => client.whoami(),
     }?;
 
-    Ok(client.had_warning())
+    Ok(if client.had_warning() { 2 } else if !found { 1 } else { 0 })
 }
 
 struct GscClientApp<'a: 'b, 'b>(clap::App<'a, 'b>);
 
+fn process_matching<'a>(matches: &clap::ArgMatches<'a>, config: &mut config::Config) {
+    for pattern in matches.values_of("EXCLUDE").into_iter().flatten() {
+        config.add_exclude_pattern(pattern.to_owned());
+    }
+
+    config.set_regex(matches.is_present("REGEX"));
+    config.set_ignore_case(matches.is_present("IGNORE_CASE"));
+}
+
+fn process_overwrite<'a>(matches: &clap::ArgMatches<'a>, config: &mut config::Config) {
+    config.set_overwrite_policy(if matches.is_present("ALWAYS") {
+        config::OverwritePolicy::Always
+    } else if matches.is_present("NEVER") {
+        config::OverwritePolicy::Never
+    } else {
+        config::OverwritePolicy::Ask
+    });
+}
+
+fn process_unpack<'a>(matches: &clap::ArgMatches<'a>, config: &mut config::Config)
+    -> Result<()> {
+
+    if let Some(strip) = matches.value_of("STRIP") {
+        config.set_strip_components(strip.parse_descr("number of components")?);
+    }
+
+    for pattern in matches.values_of("IGNORE").into_iter().flatten() {
+        config.add_ignore_arg(pattern.to_owned());
+    }
+
+    Ok(())
+}
+
 fn process_common<'a>(matches: &clap::ArgMatches<'a>, config: &mut config::Config) {
     let vs = matches.occurrences_of("VERBOSE") as isize;
     let qs = matches.occurrences_of("QUIET") as isize;
@@ -105,6 +172,26 @@ fn process_common<'a>(matches: &clap::ArgMatches<'a>, config: &mut config::Confi
     if let Some(user) = matches.value_of("ME") {
         config.set_on_behalf(user.to_owned());
     }
+
+    if matches.is_present("UTC") {
+        config.set_utc(true);
+    }
+
+    if let Some(mode) = matches.value_of("COLOR") {
+        config.set_color_mode(mode.parse().unwrap());
+    }
+
+    if let Some(format) = matches.value_of("FORMAT") {
+        config.set_output_format(format.parse().unwrap());
+    }
+
+    if matches.is_present("NO_CACHE") {
+        config.set_cache(false);
+    }
+
+    if matches.is_present("OFFLINE") {
+        config.set_offline(true);
+    }
 }
 
 impl<'a, 'b> GscClientApp<'a, 'b> {
@@ -117,39 +204,115 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
             .version(crate_version!())
             .add_common()
             .add_admin()
+            .subcommand(SubCommand::with_name("archive")
+                .about("Downloads a homework as a tar or zip archive")
+                .add_common()
+                .add_overwrite()
+                .arg(Arg::with_name("TYPE")
+                    .long("type")
+                    .short("t")
+                    .takes_value(true)
+                    .possible_values(&["tar", "tar.gz", "tgz", "zip"])
+                    .help("The kind of archive (default: from OUT’s extension, or tar for ‘-’)"))
+                .req_arg("HW", "The homework to archive, e.g. ‘hw3’")
+                .req_arg("OUT", "The archive file to write, or ‘-’ for stdout"))
             .subcommand(SubCommand::with_name("auth")
                 .about("Authenticates with the server")
                 .add_common()
                 .req_arg("USER" ,"Your username (i.e., your NetID)"))
+            .subcommand(SubCommand::with_name("cache")
+                .about("Manages the local cache of listings and file contents")
+                .add_common()
+                .subcommand(SubCommand::with_name("clear")
+                    .about("Deletes everything in the cache")
+                    .add_common()))
             .subcommand(SubCommand::with_name("cat")
                 .about("Prints remote files to stdout")
                 .add_common()
+                .add_matching()
+                .add_pager()
                 .flag("ALL", "all", "Print all files in the specified homeworks")
                 .req_args("SPEC", "The remote files or homeworks to print"))
+            .subcommand(SubCommand::with_name("check")
+                .about("Checks a submission against its manifest")
+                .add_common()
+                .arg(Arg::with_name("MANIFEST")
+                    .long("manifest")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("The manifest to check against (default: nearest ‘.gscmanifest-hwN’ or ‘.gscmanifest’)"))
+                .req_arg("HW", "The homework to check, e.g. ‘hw3’"))
             .subcommand(SubCommand::with_name("cp")
-                .about("Copies files to or from the server")
+                .about("Copies files to, from, or within the server")
                 .add_common()
+                .add_matching()
                 .flag("ALL",    "all", "Copy all the files in the specified source homeworks")
-                .flag("ALWAYS", "f",   "Overwrite existing files without asking")
-                .flag("ASK",    "i",   "Ask (interactively) before overwriting existing files")
-                .flag("NEVER",  "n",   "Never overwrite existing files")
-                .group(ArgGroup::with_name("overwrite")
-                    .args(&["ALWAYS", "ASK", "NEVER"])
-                    .multiple(false)
-                    .required(false))
+                .add_overwrite()
+                .flag("RECURSIVE", "recursive", "Upload the contents of directories, recursively")
+                .arg(Arg::with_name("EXTRACT")
+                    .short("x")
+                    .long("extract")
+                    .help("Upload the files in zip and tar archives rather than the archives"))
+                .add_force()
+                .add_show_ignored()
+                .add_unpack()
                 .req_args("SRC", "The files to copy")
                 .req_arg("DST", "The destination of the files"))
             .subcommand(SubCommand::with_name("create")
                 .about("Creates a new account")
                 .add_common()
                 .req_arg("USER", "The new account’s username (i.e., your NetID)"))
+            .subcommand(SubCommand::with_name("deadlines")
+                .about("Lists upcoming deadlines for all homeworks")
+                .add_common()
+                .arg(Arg::with_name("ICS")
+                    .long("ics")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("Also writes the deadlines to FILE in iCalendar format")))
             .subcommand(SubCommand::with_name("deauth")
                 .about("Forgets authentication credentials")
                 .add_common())
+            .subcommand(SubCommand::with_name("grep")
+                .about("Searches remote files for lines matching a regular expression")
+                .add_common()
+                .add_matching()
+                .flag("CASE",    "i", "Match PATTERN without regard to case")
+                .flag("NUMBER",  "n", "Show the line number of each match")
+                .flag("FILES",   "l", "Show only the names of files with matches")
+                .flag("COUNT",   "c", "Show only the number of matching lines in each file")
+                .req_arg("PATTERN", "The regular expression to search for")
+                .req_args("SPEC", "The homeworks or files to search, e.g. ‘hw3’"))
             .subcommand(SubCommand::with_name("ls")
                 .about("Lists files")
+                .after_help("The bracketed letter in each listing gives the file’s purpose: \
+                             [s]ource, [t]est, [c]onfig, [r]esource, or [l]og.")
                 .add_common()
+                .add_matching()
+                .add_pager()
+                .flag("LONG",    "long",    "Also show media types and full timestamps")
+                .flag("REVERSE", "reverse", "Reverse the order of the listing")
+                .flag("TIME",    "t",       "Sort by upload time, newest first")
+                .flag("SIZE",    "S",       "Sort by size, largest first")
+                .group(ArgGroup::with_name("sort")
+                    .args(&["TIME", "SIZE"])
+                    .multiple(false)
+                    .required(false))
+                .arg(Arg::with_name("PURPOSE")
+                    .long("purpose")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .value_name("PURPOSE")
+                    .possible_values(&["source", "test", "config", "resource", "log"])
+                    .help("Lists only files with this purpose (repeatable)"))
                 .req_args("SPEC", "The homeworks or files to list, e.g. ‘hw3’"))
+            .subcommand(SubCommand::with_name("mv")
+                .about("Renames a remote file")
+                .add_common()
+                .add_overwrite()
+                .req_arg("SRC", "The remote file to rename, e.g. ‘hw3:old.c’")
+                .req_arg("NAME", "The file’s new name"))
             .subcommand(SubCommand::with_name("partner")
                 .about("Manages partners")
                 .add_common()
@@ -165,15 +328,42 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
             .subcommand(SubCommand::with_name("passwd")
                 .about("Changes the password")
                 .add_common())
+            .subcommand(SubCommand::with_name("restore")
+                .about("Re-uploads files that ‘rm’ moved to the trash")
+                .add_common()
+                .add_matching()
+                .add_force()
+                .arg(Arg::with_name("SPEC")
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(false)
+                    .help("The trashed files or homeworks to restore (default: list the trash)")))
             .subcommand(SubCommand::with_name("rm")
-                .about("Removes remote files")
+                .about("Removes remote files, keeping copies in the trash")
                 .add_common()
+                .add_matching()
                 .flag("ALL", "all", "Remove all the files in the specified homework")
+                .flag("DRY_RUN", "dry-run", "List the files that would be removed, but keep them")
+                .flag("YES", "yes", "Don’t ask before removing several files")
                 .req_args("SPEC", "The remote files or homeworks to remove"))
             .subcommand(SubCommand::with_name("status")
                 .about("Retrieves user or submission status")
                 .add_common()
+                .add_pager()
                 .opt_arg("HW", "The homework to lookup, e.g. ‘hw3’"))
+            .subcommand(SubCommand::with_name("submit")
+                .about("Uploads a project directory")
+                .add_common()
+                .add_force()
+                .add_show_ignored()
+                .add_unpack()
+                .req_arg("HW", "The homework to submit to, e.g. ‘hw3’")
+                .opt_arg("DIR", "The project directory or archive (default: ‘.’)"))
+            .subcommand(SubCommand::with_name("verify")
+                .about("Compares remote files with a local project directory")
+                .add_common()
+                .req_arg("HW", "The homework to verify, e.g. ‘hw3’")
+                .opt_arg("DIR", "The project directory (default: ‘.’)"))
             .subcommand(SubCommand::with_name("whoami")
                 .about("Prints your username, if authenticated")
                 .add_common()))
@@ -201,6 +391,16 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
                 let user = subsubmatches.value_of("USER").unwrap().to_owned();
                 let date = subsubmatches.value_of("DATESPEC").unwrap().to_owned();
                 Ok(Command::AdminExtend { hw, user, date, eval })
+            } else if let Some(subsubmatches) = submatches.subcommand_matches("gradebook") {
+                process_common(subsubmatches, config);
+                let policy = subsubmatches.value_of_os("POLICY").map(PathBuf::from);
+                let filter = GradebookFilter {
+                    sections: subsubmatches.values_of("SECTION").into_iter().flatten()
+                        .map(str::to_owned).collect(),
+                    users:    subsubmatches.values_of("STUDENT").into_iter().flatten()
+                        .map(str::to_owned).collect(),
+                };
+                Ok(Command::AdminGradebook { policy, filter })
             } else if let Some(subsubmatches) = submatches.subcommand_matches("partners") {
                 process_common(subsubmatches, config);
                 let hw   = parse_hw(subsubmatches.value_of("HW").unwrap())?;
@@ -237,29 +437,65 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
             }
         }
 
+        else if let Some(submatches) = matches.subcommand_matches("archive") {
+            process_common(submatches, config);
+            process_overwrite(submatches, config);
+
+            let hw     = parse_hw(submatches.value_of("HW").unwrap())?;
+            let dst    = PathBuf::from(submatches.value_of_os("OUT").unwrap());
+            let format = match submatches.value_of("TYPE") {
+                Some(format)                   => format.parse()?,
+                None if dst.as_os_str() == "-" => archive::ArchiveFormat::Tar,
+                None                           => archive::ArchiveFormat::from_path(&dst)
+                    .ok_or_else(|| ErrorKind::UnknownArchiveFormat(dst.clone()))?,
+            };
+
+            Ok(Command::Archive{hw, dst, format})
+        }
+
         else if let Some(submatches) = matches.subcommand_matches("auth") {
             process_common(submatches, config);
             let user = submatches.value_of("USER").unwrap().to_owned();
             Ok(Command::Auth{user})
         }
 
+        else if let Some(submatches) = matches.subcommand_matches("cache") {
+            process_common(submatches, config);
+
+            if let Some(subsubmatches) = submatches.subcommand_matches("clear") {
+                process_common(subsubmatches, config);
+                Ok(Command::CacheClear)
+            } else {
+                Err(ErrorKind::NoCommandGiven.into())
+            }
+        }
+
         else if let Some(submatches) = matches.subcommand_matches("cat") {
             process_common(submatches, config);
+            process_matching(submatches, config);
+            config.set_pager(!submatches.is_present("NO_PAGER"));
             let all = submatches.is_present("ALL");
 
-            let mut rpats = Vec::new();
+            let mut rspecs = Vec::new();
 
             for arg in submatches.values_of("SPEC").unwrap() {
-                let rpat = parse_hw_opt_file(arg)?;
+                let rspec = parse_hw_opt_file(arg)?;
 
-                if rpat.is_whole_hw() && !all {
+                if rspec.is_whole_hw() && !all {
                     Err(ErrorKind::CommandRequiresFlag("cat".to_owned()))?;
                 }
 
-                rpats.push(rpat);
+                rspecs.push(rspec);
             }
 
-            Ok(Command::Cat{rpats})
+            Ok(Command::Cat{rspecs})
+        }
+
+        else if let Some(submatches) = matches.subcommand_matches("check") {
+            process_common(submatches, config);
+            let hw       = parse_hw(submatches.value_of("HW").unwrap())?;
+            let manifest = submatches.value_of_os("MANIFEST").map(PathBuf::from);
+            Ok(Command::Check{hw, manifest})
         }
 
         else if let Some(submatches) = matches.subcommand_matches("create") {
@@ -270,15 +506,16 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
 
         else if let Some(submatches) = matches.subcommand_matches("cp") {
             process_common(submatches, config);
+            process_matching(submatches, config);
             let all       = submatches.is_present("ALL");
 
-            config.set_overwrite_policy(if submatches.is_present("ALWAYS") {
-                config::OverwritePolicy::Always
-            } else if submatches.is_present("NEVER") {
-                config::OverwritePolicy::Never
-            } else {
-                config::OverwritePolicy::Ask
-            });
+            config.set_force(submatches.is_present("FORCE"));
+            config.set_recursive(submatches.is_present("RECURSIVE"));
+            config.set_extract(submatches.is_present("EXTRACT"));
+            config.set_show_ignored(submatches.is_present("SHOW_IGNORED"));
+            config.set_no_ignore(submatches.is_present("NO_IGNORE"));
+            process_unpack(submatches, config)?;
+            process_overwrite(submatches, config);
 
             let mut srcs  = Vec::new();
             let dst       = parse_cp_arg(submatches.value_of("DST").unwrap())?;
@@ -296,22 +533,93 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
             Ok(Command::Cp{srcs, dst})
         }
 
+        else if let Some(submatches) = matches.subcommand_matches("deadlines") {
+            process_common(submatches, config);
+            let ics = submatches.value_of_os("ICS").map(PathBuf::from);
+            Ok(Command::Deadlines{ics})
+        }
+
         else if let Some(submatches) = matches.subcommand_matches("deauth") {
             process_common(submatches, config);
             Ok(Command::Deauth)
         }
 
+        else if let Some(submatches) = matches.subcommand_matches("grep") {
+            process_common(submatches, config);
+            process_matching(submatches, config);
+
+            let pattern    = submatches.value_of("PATTERN").unwrap().to_owned();
+            let mut rspecs = Vec::new();
+
+            for arg in submatches.values_of("SPEC").unwrap() {
+                rspecs.push(parse_hw_opt_file(arg)?);
+            }
+
+            let options = GrepOptions {
+                ignore_case:    submatches.is_present("CASE"),
+                line_numbers:   submatches.is_present("NUMBER"),
+                files_only:     submatches.is_present("FILES"),
+                count:          submatches.is_present("COUNT"),
+            };
+
+            Ok(Command::Grep{pattern, rspecs, options})
+        }
+
         else if let Some(submatches) = matches.subcommand_matches("ls") {
             process_common(submatches, config);
+            process_matching(submatches, config);
+            config.set_pager(!submatches.is_present("NO_PAGER"));
             
             let ls_specs   = submatches.values_of("SPEC").unwrap();
-            let mut rpats = Vec::new();
+            let mut rspecs = Vec::new();
 
             for ls_spec in ls_specs {
-                rpats.push(parse_hw_opt_file(ls_spec)?);
+                rspecs.push(parse_hw_opt_file(ls_spec)?);
             }
 
-            Ok(Command::Ls{rpats})
+            let sort = if submatches.is_present("TIME") {
+                LsSort::Time
+            } else if submatches.is_present("SIZE") {
+                LsSort::Size
+            } else {
+                LsSort::Server
+            };
+
+            let purposes = submatches.values_of("PURPOSE").into_iter().flatten()
+                .map(str::parse)
+                .collect::<Result<_>>()?;
+
+            let options = LsOptions {
+                sort,
+                reverse:  submatches.is_present("REVERSE"),
+                purposes,
+                long:     submatches.is_present("LONG"),
+            };
+
+            Ok(Command::Ls{rspecs, options})
+        }
+
+        else if let Some(submatches) = matches.subcommand_matches("mv") {
+            process_common(submatches, config);
+            process_overwrite(submatches, config);
+
+            let src  = parse_hw_file(submatches.value_of("SRC").unwrap())?;
+            let hw   = match src.hws.single() {
+                Some(hw) if !src.is_whole_hw() => hw,
+                _                              => Err(syntax_error("remote file", src.to_string()))?,
+            };
+
+            // The new name may repeat the homework, as in ‘hw3:new.c’.
+            let name = submatches.value_of("NAME").unwrap();
+            let name = match parse_hw_file(name) {
+                Ok(dst) if dst.hws.single() == Some(hw) && !dst.is_whole_hw() => dst.pat,
+                Ok(_)                                                          =>
+                    Err(syntax_error("new file name in the same homework", name))?,
+                Err(_) if !name.is_empty()                                     => name.to_owned(),
+                Err(error)                                                     => Err(error)?,
+            };
+
+            Ok(Command::Mv{src: src.for_hw(hw), name})
         }
 
         else if let Some(submatches) = matches.subcommand_matches("partner") {
@@ -343,26 +651,44 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
             Ok(Command::Passwd)
         }
 
+        else if let Some(submatches) = matches.subcommand_matches("restore") {
+            process_common(submatches, config);
+            process_matching(submatches, config);
+            config.set_force(submatches.is_present("FORCE"));
+
+            let mut rspecs = Vec::new();
+
+            for arg in submatches.values_of("SPEC").into_iter().flatten() {
+                rspecs.push(parse_hw_opt_file(arg)?);
+            }
+
+            Ok(Command::Restore{rspecs})
+        }
+
         else if let Some(submatches) = matches.subcommand_matches("rm") {
             process_common(submatches, config);
+            process_matching(submatches, config);
+            config.set_dry_run(submatches.is_present("DRY_RUN"));
+            config.set_yes(submatches.is_present("YES"));
             let all       = submatches.is_present("ALL");
-            let mut rpats = Vec::new();
+            let mut rspecs = Vec::new();
 
             for arg in submatches.values_of("SPEC").unwrap() {
-                let rpat = parse_hw_opt_file(arg)?;
+                let rspec = parse_hw_opt_file(arg)?;
 
-                if rpat.is_whole_hw() && !all {
+                if rspec.is_whole_hw() && !all {
                     Err(ErrorKind::CommandRequiresFlag("rm".to_owned()))?;
                 }
 
-                rpats.push(rpat);
+                rspecs.push(rspec);
             }
 
-            Ok(Command::Rm{rpats})
+            Ok(Command::Rm{rspecs})
         }
 
         else if let Some(submatches) = matches.subcommand_matches("status") {
             process_common(submatches, config);
+            config.set_pager(!submatches.is_present("NO_PAGER"));
             let hw   = match submatches.value_of("HW") {
                 Some(hw_spec) => Some(parse_hw(hw_spec)?),
                 None          => None,
@@ -370,6 +696,26 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
             Ok(Command::Status{hw})
         }
 
+        else if let Some(submatches) = matches.subcommand_matches("submit") {
+            process_common(submatches, config);
+            config.set_force(submatches.is_present("FORCE"));
+            config.set_show_ignored(submatches.is_present("SHOW_IGNORED"));
+            config.set_no_ignore(submatches.is_present("NO_IGNORE"));
+            process_unpack(submatches, config)?;
+            let hw  = parse_hw(submatches.value_of("HW").unwrap())?;
+            let dir = submatches.value_of_os("DIR").map_or_else(|| PathBuf::from("."),
+                                                                PathBuf::from);
+            Ok(Command::Submit{hw, dir})
+        }
+
+        else if let Some(submatches) = matches.subcommand_matches("verify") {
+            process_common(submatches, config);
+            let hw  = parse_hw(submatches.value_of("HW").unwrap())?;
+            let dir = submatches.value_of_os("DIR").map_or_else(|| PathBuf::from("."),
+                                                                PathBuf::from);
+            Ok(Command::Verify{hw, dir})
+        }
+
         else if let Some(submatches) = matches.subcommand_matches("whoami") {
             process_common(submatches, config);
             Ok(Command::Whoami)
@@ -384,7 +730,13 @@ impl<'a, 'b> GscClientApp<'a, 'b> {
 trait AppExt {
     fn add_admin(self) -> Self;
     fn add_common(self) -> Self;
+    fn add_force(self) -> Self;
+    fn add_matching(self) -> Self;
+    fn add_pager(self) -> Self;
+    fn add_overwrite(self) -> Self;
     fn add_partner_args(self) -> Self;
+    fn add_show_ignored(self) -> Self;
+    fn add_unpack(self) -> Self;
     fn add_user_opt(self) -> Self;
 
     // An optional positional argument:
@@ -420,6 +772,24 @@ impl<'a, 'b> AppExt for clap::App<'a, 'b> {
                 .req_arg("HW", "The homework to extend")
                 .req_arg("USER", "The user to extend")
                 .req_arg("DATESPEC", "The new due date"))
+            .subcommand(SubCommand::with_name("gradebook")
+                .about("Computes final grades from the grade spreadsheet")
+                .add_common()
+                .arg(Arg::with_name("POLICY")
+                    .long("policy")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("The grading policy (default: ‘grading_policy’ from the dotfile)"))
+                .arg(Arg::with_name("SECTION")
+                    .long("section")
+                    .takes_value(true)
+                    .value_name("SECTION")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Only reports students in SECTION (repeatable)"))
+                .arg(Arg::with_name("STUDENT")
+                    .multiple(true)
+                    .help("Only reports these students")))
             .subcommand(SubCommand::with_name("partners")
                 .about("Looks up a partnership")
                 .add_common()
@@ -471,15 +841,107 @@ impl<'a, 'b> AppExt for clap::App<'a, 'b> {
                 .multiple(true)
                 .takes_value(false)
                 .help("Makes the output quieter"))
+            .arg(clap::Arg::with_name("FORMAT")
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["table", "json", "yaml", "csv"])
+                .help("Prints listings as a table (the default) or as JSON, YAML, or CSV"))
+            .arg(clap::Arg::with_name("COLOR")
+                .long("color")
+                .takes_value(true)
+                .value_name("WHEN")
+                .possible_values(&["always", "never", "auto"])
+                .help("Colors the output: always, never, or auto (only on a terminal)"))
+            .arg(clap::Arg::with_name("UTC")
+                .long("utc")
+                .takes_value(false)
+                .help("Displays dates in UTC rather than the local timezone"))
+            .arg(clap::Arg::with_name("NO_CACHE")
+                .long("no-cache")
+                .takes_value(false)
+                .help("Always asks the server rather than using cached listings and files"))
+            .arg(clap::Arg::with_name("OFFLINE")
+                .long("offline")
+                .takes_value(false)
+                .help("Shows the listings and files saved by earlier runs, without the server"))
             .add_user_opt()
     }
 
+    fn add_overwrite(self) -> Self {
+        self.flag("ALWAYS", "f", "Overwrite existing files without asking")
+            .flag("ASK",    "i", "Ask (interactively) before overwriting existing files")
+            .flag("NEVER",  "n", "Never overwrite existing files")
+            .group(clap::ArgGroup::with_name("overwrite")
+                .args(&["ALWAYS", "ASK", "NEVER"])
+                .multiple(false)
+                .required(false))
+    }
+
+    fn add_pager(self) -> Self {
+        self.arg(clap::Arg::with_name("NO_PAGER")
+            .long("no-pager")
+            .takes_value(false)
+            .help("Prints directly rather than through $PAGER"))
+    }
+
     fn add_partner_args(self) -> Self {
         self.add_common()
             .req_arg("HW", "The homework of the partner request")
             .req_arg("USER", "The other user of the partner request")
     }
 
+    fn add_force(self) -> Self {
+        self.arg(clap::Arg::with_name("FORCE")
+            .long("force")
+            .takes_value(false)
+            .help("Uploads even if the files would exceed the submission’s quota"))
+    }
+
+    fn add_matching(self) -> Self {
+        self.arg(clap::Arg::with_name("EXCLUDE")
+                .long("exclude")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("GLOB")
+                .help("Skips remote files matching GLOB (repeatable)"))
+            .arg(clap::Arg::with_name("REGEX")
+                .long("regex")
+                .takes_value(false)
+                .help("Treats remote patterns as regular expressions rather than globs"))
+            .arg(clap::Arg::with_name("IGNORE_CASE")
+                .long("ignore-name-case")
+                .takes_value(false)
+                .help("Matches remote file names without regard to case"))
+    }
+
+    fn add_show_ignored(self) -> Self {
+        self.arg(clap::Arg::with_name("SHOW_IGNORED")
+                .long("show-ignored")
+                .takes_value(false)
+                .help("Lists the files skipped because of ‘.gscignore’ or the dotfile"))
+            .arg(clap::Arg::with_name("NO_IGNORE")
+                .long("no-ignore")
+                .takes_value(false)
+                .help("Uploads files even if ‘.gscignore’ or the dotfile would skip them"))
+    }
+
+    fn add_unpack(self) -> Self {
+        self.arg(clap::Arg::with_name("STRIP")
+                .long("strip-components")
+                .takes_value(true)
+                .value_name("N")
+                .help("Drops the first N directories from the paths in an archive"))
+            .arg(clap::Arg::with_name("IGNORE")
+                .long("ignore")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("PATTERN")
+                .help("Skips local files matching PATTERN, as in ‘.gscignore’ (repeatable)"))
+    }
+
     #[cfg(feature = "admin")]
     fn add_user_opt(self) -> Self {
         self.arg(clap::Arg::with_name("ME")
@@ -533,18 +995,6 @@ impl<'a, 'b> AppExt for clap::App<'a, 'b> {
     }
 }
 
-mod re {
-    use lazy_static::lazy_static;
-    use regex::Regex;
-
-    lazy_static! {
-        pub static ref HW_ONLY:         Regex = Regex::new(r"^hw(\d+):?$").unwrap();
-        pub static ref HW_OPT_FILE:     Regex = Regex::new(r"^hw(\d+)(?::(.*))?$").unwrap();
-        pub static ref HW_FILE:         Regex = Regex::new(r"^hw(\d+):(.*)$").unwrap();
-        pub static ref LOCAL_FILE:      Regex = Regex::new(r"^:(.+)$").unwrap();
-    }
-}
-
 trait ParseWithDescription {
     fn parse_descr<F: FromStr>(&self, descr: &str) -> Result<F>
         where <F as FromStr>::Err: std::error::Error + Send + 'static;
@@ -557,48 +1007,3 @@ impl ParseWithDescription for str {
         self.parse().chain_err(|| syntax_error(descr, self))
     }
 }
-
-fn parse_hw(spec: &str) -> Result<usize> {
-    if let Some(i) = re::HW_ONLY.captures(spec)
-        .and_then(|captures| captures.get(1))
-        .and_then(|s| s.as_str().parse().ok()) {
-        Ok(i)
-    } else {
-        Err(syntax_error("homework spec", spec))?
-    }
-}
-
-fn parse_hw_opt_file(spec: &str) -> Result<RemotePattern> {
-    let captures  = re::HW_OPT_FILE.captures(spec)
-        .ok_or_else(|| syntax_error("homework or file spec", spec))?;
-    let capture1  = captures.get(1).unwrap().as_str();
-    let capture2  = captures.get(2).map(|c| c.as_str());
-    let hw        = capture1.parse().unwrap();
-    let pat       = capture2.unwrap_or("").to_owned();
-    Ok(RemotePattern{hw, pat})
-}
-
-fn parse_hw_file(file_spec: &str) -> Result<RemotePattern> {
-    let captures  = re::HW_FILE.captures(file_spec)
-        .ok_or_else(|| syntax_error("remote file or homework spec", file_spec))?;
-    let capture1  = captures.get(1).unwrap().as_str();
-    let capture2  = captures.get(2).unwrap().as_str();
-    let hw        = capture1.parse().unwrap();
-    let pat       = capture2.to_owned();
-    Ok(RemotePattern{hw, pat})
-}
-
-fn parse_cp_arg(spec: &str) -> Result<CpArg> {
-    if spec.is_empty() {
-        Err(syntax_error("file name", spec))?
-    } else if let Some(captures) = re::LOCAL_FILE.captures(spec) {
-        let filename = captures.get(1).unwrap().as_str().to_owned();
-        Ok(CpArg::Local(filename.into()))
-    } else if let Some(_) = spec.find(':') {
-        let rp = parse_hw_file(spec)?;
-        Ok(CpArg::Remote(rp))
-    } else {
-        Ok(CpArg::Local(spec.into()))
-    }
-}
-
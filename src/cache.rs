@@ -0,0 +1,250 @@
+//! The local cache of server listings and file contents.
+//!
+//! Listings are saved as JSON under `listings`, named by a hash of the URI
+//! they came from and stamped with the time they were saved. They are used
+//! only while younger than the TTL, and not at all once we change something
+//! on the server, but they are kept for offline use. File contents are
+//! saved under `contents`, named by a hash of the file’s URI, upload time,
+//! and size, so a file that changes on the server gets a new entry rather
+//! than a stale one. Saved contents are deleted once they are a week old.
+//!
+//! The cache is only an optimization, so failing to read or write it is
+//! logged rather than reported as an error.
+
+use super::errors::*;
+use super::messages::{DateTime, FileMeta};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_derive::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use vlog::*;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime};
+
+const CONTENTS_DIR: &str = "contents";
+const LISTINGS_DIR: &str = "listings";
+const STALE_NAME: &str   = "stale-before";
+
+const CONTENTS_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A cached value, along with when it was saved.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Saved<T> {
+    pub saved_time:     DateTime,
+    pub value:          T,
+}
+
+#[derive(Debug)]
+pub struct Cache {
+    dir:        PathBuf,
+    ttl:        chrono::Duration,
+}
+
+impl<T> Saved<T> {
+    /// How long ago the value was saved.
+    pub fn age(&self) -> chrono::Duration {
+        -self.saved_time.from_now()
+    }
+}
+
+impl Cache {
+    /// A cache in `dir` whose listings stay fresh for `ttl` seconds.
+    pub fn new(dir: &Path, ttl: u64) -> Self {
+        Cache {
+            dir: dir.to_owned(),
+            ttl: chrono::Duration::seconds(ttl.min(i64::MAX as u64) as i64),
+        }
+    }
+
+    /// The listing saved from `uri`, if it is still fresh.
+    pub fn get_listing<T: DeserializeOwned>(&self, uri: &str) -> Option<T> {
+        let stale_before = self.stale_before();
+
+        self.get_saved_listing(uri)
+            .filter(|saved| saved.age() < self.ttl)
+            .filter(|saved| stale_before.is_none_or(|time| saved.saved_time.to_utc() > time))
+            .map(|saved| saved.value)
+    }
+
+    /// The listing saved from `uri`, however old.
+    pub fn get_saved_listing<T: DeserializeOwned>(&self, uri: &str) -> Option<Saved<T>> {
+        let path  = self.listing_path(uri);
+        let bytes = self.read(&path)?;
+
+        match serde_json::from_slice(&bytes) {
+            Ok(saved)  => Some(saved),
+            Err(error) => {
+                ve3!("Ignoring unreadable cache entry ‘{}’: {}", path.display(), error);
+                None
+            }
+        }
+    }
+
+    /// Saves the listing fetched from `uri`.
+    pub fn put_listing<T: Serialize>(&self, uri: &str, value: &T) {
+        let saved = Saved { saved_time: DateTime::now(), value };
+
+        match serde_json::to_vec(&saved) {
+            Ok(bytes)  => self.write(&self.listing_path(uri), &bytes),
+            Err(error) => ve3!("Could not cache ‘{}’: {}", uri, error),
+        }
+    }
+
+    /// Marks every listing saved so far as stale, since the server has
+    /// changed.
+    pub fn forget_listings(&self) {
+        self.write(&self.dir.join(STALE_NAME), DateTime::now().to_rfc3339().as_bytes());
+    }
+
+    /// The contents of the file at `uri`, if we have this version of it.
+    pub fn get_contents(&self, uri: &str, meta: &FileMeta) -> Option<Vec<u8>> {
+        let path     = self.contents_path(uri, meta);
+        let contents = self.read(&path)?;
+
+        if contents.len() == meta.byte_count {
+            Some(contents)
+        } else {
+            ve3!("Ignoring truncated cache entry ‘{}’.", path.display());
+            None
+        }
+    }
+
+    /// Saves the contents of the file at `uri`, first deleting any saved
+    /// contents that have grown too old.
+    pub fn put_contents(&self, uri: &str, meta: &FileMeta, contents: &[u8]) {
+        self.prune_contents();
+        self.write(&self.contents_path(uri, meta), contents);
+    }
+
+    /// Deletes everything in the cache.
+    pub fn clear(&self) -> Result<()> {
+        remove_dir(&self.dir)
+            .chain_err(|| format!("Could not remove cache ‘{}’", self.dir.display()))?;
+        Ok(())
+    }
+
+    // When `forget_listings` was last called, if ever.
+    fn stale_before(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let bytes = fs::read(self.dir.join(STALE_NAME)).ok()?;
+        let time  = chrono::DateTime::parse_from_rfc3339(String::from_utf8_lossy(&bytes).trim());
+        time.ok().map(|time| time.with_timezone(&chrono::Utc))
+    }
+
+    fn prune_contents(&self) {
+        let dir      = self.dir.join(CONTENTS_DIR);
+        let read_dir = match fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(_)       => return,
+        };
+        let now      = SystemTime::now();
+
+        for entry in read_dir.flatten() {
+            let expired = entry.metadata()
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| now.duration_since(modified)
+                     .is_ok_and(|age| age > CONTENTS_MAX_AGE))
+                .unwrap_or(false);
+
+            if expired {
+                let path = entry.path();
+                ve3!("Removing old cache entry ‘{}’.", path.display());
+                if let Err(error) = fs::remove_file(&path) {
+                    ve3!("Could not remove cache entry ‘{}’: {}", path.display(), error);
+                }
+            }
+        }
+    }
+
+    fn listing_path(&self, uri: &str) -> PathBuf {
+        self.dir.join(LISTINGS_DIR).join(hash_key(uri) + ".json")
+    }
+
+    fn contents_path(&self, uri: &str, meta: &FileMeta) -> PathBuf {
+        let key = format!("{}\n{}\n{}", uri, meta.upload_time.to_rfc3339(), meta.byte_count);
+        self.dir.join(CONTENTS_DIR).join(hash_key(&key))
+    }
+
+    fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        match fs::read(path) {
+            Ok(bytes)  => {
+                ve3!("< Using cached ‘{}’", path.display());
+                Some(bytes)
+            }
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    ve3!("Could not read cache entry ‘{}’: {}", path.display(), error);
+                }
+                None
+            }
+        }
+    }
+
+    // Writes to a temporary file first, so that an interrupted write never
+    // leaves a partial entry behind.
+    fn write(&self, path: &Path, bytes: &[u8]) {
+        let tmp    = path.with_extension(format!("tmp{}", process::id()));
+        let result = (|| -> io::Result<()> {
+            if let Some(parent) = path.parent() {
+                create_private_dir(parent)?;
+            }
+
+            let mut file = create_private_file(&tmp)?;
+            file.write_all(bytes)?;
+            fs::rename(&tmp, path)
+        })();
+
+        if let Err(error) = result {
+            let _ = fs::remove_file(&tmp);
+            ve3!("Could not write cache entry ‘{}’: {}", path.display(), error);
+        }
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Creates `dir` and any missing parents, readable only by the user, since
+/// the cache and the trash hold the user’s files.
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    private_dir_builder().recursive(true).create(dir)
+}
+
+/// A builder for directories readable only by the user.
+pub fn private_dir_builder() -> fs::DirBuilder {
+    let mut builder = fs::DirBuilder::new();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    builder
+}
+
+/// Creates (or truncates) the file at `path`, readable only by the user.
+pub fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)
+}
+
+// Removes `dir` and everything in it, if it exists.
+fn remove_dir(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result                                            => result,
+    }
+}
@@ -0,0 +1,166 @@
+//! Homework deadlines, and their export as iCalendar (RFC 5545).
+
+use super::messages::{DateTime, Submission, SubmissionStatus};
+
+use serde_derive::Serialize;
+
+use std::io::{self, Write};
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlineKind {
+    Open,
+    Due,
+    Eval,
+}
+
+/// One date in the life of a homework.
+#[derive(Serialize, Clone, Debug)]
+pub struct Deadline {
+    pub hw:         usize,
+    pub kind:       DeadlineKind,
+    pub date:       DateTime,
+    /// Whether the user has an extension for this deadline.
+    pub extended:   bool,
+}
+
+/// The open, due, and self-eval dates of a submission that are still to
+/// come. These are the user’s own dates, so they already account for any
+/// extensions. The server doesn’t send the assignment’s original dates, so
+/// a deadline is only known to be extended once the submission’s status
+/// says so, that is, after the original deadline has passed.
+pub fn submission_deadlines(submission: &Submission) -> Vec<Deadline> {
+    let hw = submission.assignment_number;
+
+    vec![
+        Deadline {
+            hw,
+            kind:     DeadlineKind::Open,
+            date:     submission.open_date.clone(),
+            extended: false,
+        },
+        Deadline {
+            hw,
+            kind:     DeadlineKind::Due,
+            date:     submission.due_date.clone(),
+            extended: submission.status == SubmissionStatus::Extended,
+        },
+        Deadline {
+            hw,
+            kind:     DeadlineKind::Eval,
+            date:     submission.eval_date.clone(),
+            extended: submission.status == SubmissionStatus::ExtendedEval,
+        },
+    ].into_iter()
+        .filter(|deadline| deadline.date.from_now() > chrono::Duration::zero())
+        .collect()
+}
+
+impl DeadlineKind {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            DeadlineKind::Open => "opens",
+            DeadlineKind::Due  => "due",
+            DeadlineKind::Eval => "self-eval due",
+        }
+    }
+
+    fn to_uid_part(self) -> &'static str {
+        match self {
+            DeadlineKind::Open => "open",
+            DeadlineKind::Due  => "due",
+            DeadlineKind::Eval => "eval",
+        }
+    }
+
+    // Alarm triggers, relative to the event’s start.
+    fn alarms(self) -> &'static [&'static str] {
+        match self {
+            DeadlineKind::Open => &["PT0M"],
+            DeadlineKind::Due  => &["-P1D", "-PT1H"],
+            DeadlineKind::Eval => &["-P1D", "-PT1H"],
+        }
+    }
+}
+
+impl Deadline {
+    pub fn summary(&self) -> String {
+        let extended = if self.extended { " (extended)" } else { "" };
+        format!("hw{} {}{}", self.hw, self.kind.to_str(), extended)
+    }
+}
+
+const ICS_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Writes `deadlines` as an iCalendar file, with one event per deadline.
+/// Event UIDs depend only on `user`, the homework, and the kind of
+/// deadline, so re-importing an updated file replaces the old events.
+pub fn write_ics<W: Write>(mut out: W, user: &str, deadlines: &[Deadline]) -> io::Result<()> {
+    let now = chrono::Utc::now().format(ICS_DATE_FORMAT);
+
+    write_line(&mut out, "BEGIN:VCALENDAR")?;
+    write_line(&mut out, "VERSION:2.0")?;
+    write_line(&mut out, "PRODID:-//gsc-client//deadlines//EN")?;
+    write_line(&mut out, "CALSCALE:GREGORIAN")?;
+    write_line(&mut out, "METHOD:PUBLISH")?;
+
+    for deadline in deadlines {
+        let summary = escape_text(&deadline.summary());
+
+        write_line(&mut out, "BEGIN:VEVENT")?;
+        write_line(&mut out, &format!("UID:{}-hw{}-{}@gsc",
+                                      escape_text(user),
+                                      deadline.hw,
+                                      deadline.kind.to_uid_part()))?;
+        write_line(&mut out, &format!("DTSTAMP:{}", now))?;
+        write_line(&mut out, &format!("DTSTART:{}",
+                                      deadline.date.to_utc().format(ICS_DATE_FORMAT)))?;
+        write_line(&mut out, &format!("SUMMARY:{}", summary))?;
+
+        for trigger in deadline.kind.alarms() {
+            write_line(&mut out, "BEGIN:VALARM")?;
+            write_line(&mut out, "ACTION:DISPLAY")?;
+            write_line(&mut out, &format!("TRIGGER:{}", trigger))?;
+            write_line(&mut out, &format!("DESCRIPTION:{}", summary))?;
+            write_line(&mut out, "END:VALARM")?;
+        }
+
+        write_line(&mut out, "END:VEVENT")?;
+    }
+
+    write_line(&mut out, "END:VCALENDAR")
+}
+
+// iCalendar lines end in CRLF and are folded at 75 octets.
+fn write_line<W: Write>(out: &mut W, line: &str) -> io::Result<()> {
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.write_all(b"\r\n ")?;
+            width = 1;
+        }
+
+        write!(out, "{}", c)?;
+        width += c.len_utf8();
+    }
+
+    out.write_all(b"\r\n")
+}
+
+fn escape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n'             => result.push_str("\\n"),
+            _                => result.push(c),
+        }
+    }
+
+    result
+}
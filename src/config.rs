@@ -3,6 +3,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use super::errors::*;
+use super::messages;
+use super::style;
 
 use serde_derive::Deserialize;
 use serde_yaml;
@@ -15,14 +17,48 @@ const COOKIEFILE_NAME: &str = ".gsclogin";
 const DOTFILE_VAR: &str     = "GSC_DOTFILE";
 const DOTFILE_NAME: &str    = ".gscrc";
 
+const TOKEN_VAR: &str       = "GSC_TOKEN";
+
+const TRASH_VAR: &str       = "GSC_TRASH";
+
+const CACHE_VAR: &str       = "GSC_CACHE";
+
+const DEFAULT_CACHE_TTL: u64 = 60;
+
 #[derive(Debug)]
 pub struct Config {
+    cache:       bool,
+    cache_dir:   Option<PathBuf>,
+    cache_ttl:   u64,
+    color:       ColorMode,
     cookie_file: Option<PathBuf>,
+    date_format: String,
     dotfile:     Option<PathBuf>,
+    dry_run:     bool,
     endpoint:    String,
+    exclude:     Vec<String>,
+    extract:     bool,
+    force:       bool,
+    format:      OutputFormat,
+    grading_policy: Option<PathBuf>,
+    ignore:      Vec<String>,
+    ignore_args: Vec<String>,
+    ignore_case: bool,
+    manifest_dir: Option<PathBuf>,
+    offline:     bool,
     on_behalf:   Option<String>,
     overwrite:   OverwritePolicy,
+    pager:       bool,
+    recursive:   bool,
+    regex:       bool,
+    show_ignored: bool,
+    no_ignore: bool,
+    strip_components: usize,
+    token:       Option<String>,
+    trash_dir:   Option<PathBuf>,
+    utc:         bool,
     verbosity:   isize,
+    yes:         bool,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -32,6 +68,23 @@ pub enum OverwritePolicy {
     Ask,
 }
 
+/// When to color output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorMode {
+    Always,
+    Never,
+    Auto,
+}
+
+/// How listings are printed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
 /// This is the format of the dotfile.
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -40,6 +93,18 @@ pub struct Dotfile {
     pub endpoint:   String,
     #[serde(default)]
     pub verbosity:  Option<isize>,
+    #[serde(default)]
+    pub token:      Option<String>,
+    #[serde(default)]
+    pub date_format: Option<String>,
+    #[serde(default)]
+    pub manifest_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub ignore:     Vec<String>,
+    #[serde(default)]
+    pub cache_ttl:  Option<u64>,
+    #[serde(default)]
+    pub grading_policy: Option<PathBuf>,
 }
 
 fn find_dotfile(env_var: &str, filename: &str) -> Option<PathBuf> {
@@ -56,21 +121,207 @@ fn find_dotfile(env_var: &str, filename: &str) -> Option<PathBuf> {
     }
 }
 
+// The trash goes in `$GSC_TRASH` if set, or else under the XDG data
+// directory.
+fn find_trash_dir() -> Option<PathBuf> {
+    match env::var_os(TRASH_VAR) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None      => find_xdg_dir("XDG_DATA_HOME", &[".local", "share"])
+            .map(|dir| dir.join("trash")),
+    }
+}
+
+// The cache goes in `$GSC_CACHE` if set, or else in the XDG cache
+// directory.
+fn find_cache_dir() -> Option<PathBuf> {
+    match env::var_os(CACHE_VAR) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None      => find_xdg_dir("XDG_CACHE_HOME", &[".cache"]),
+    }
+}
+
+// Our directory under `$xdg_var`, which defaults to `fallback` under the
+// home directory.
+fn find_xdg_dir(xdg_var: &str, fallback: &[&str]) -> Option<PathBuf> {
+    let base = match env::var_os(xdg_var).filter(|s| !s.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None      => fallback.iter().fold(PathBuf::from(env::var_os("HOME")?),
+                                          |dir, part| dir.join(part)),
+    };
+
+    Some(base.join("gsc"))
+}
+
+// Rejects formats that chrono can’t render, since it would otherwise fail
+// only once we try to display a date.
+fn check_date_format(format: &str) -> Result<()> {
+    use chrono::format::{Item, StrftimeItems};
+
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        Err(syntax_error("date format", format))?
+    } else {
+        Ok(())
+    }
+}
+
 impl Config {
     pub fn new() -> Self {
         let cookie_file = find_dotfile(COOKIEFILE_VAR, COOKIEFILE_NAME);
         let dotfile     = find_dotfile(DOTFILE_VAR, DOTFILE_NAME);
+        let token       = env::var(TOKEN_VAR).ok().filter(|s| !s.is_empty());
+        let trash_dir   = find_trash_dir();
+        let cache_dir   = find_cache_dir();
 
         Config {
+            cache:       true,
+            cache_dir,
+            cache_ttl:   DEFAULT_CACHE_TTL,
+            color:       ColorMode::Auto,
             cookie_file,
+            date_format: messages::DEFAULT_DATE_FORMAT.to_owned(),
             dotfile,
+            dry_run:     false,
             endpoint:    API_ENDPOINT.to_owned(),
+            exclude:     Vec::new(),
+            extract:     false,
+            force:       false,
+            format:      OutputFormat::Table,
+            grading_policy: None,
+            ignore:      Vec::new(),
+            ignore_args: Vec::new(),
+            ignore_case: false,
+            manifest_dir: None,
+            offline:     false,
             on_behalf:   None,
             overwrite:   OverwritePolicy::Ask,
+            pager:       true,
+            recursive:   false,
+            regex:       false,
+            show_ignored: false,
+            no_ignore: false,
+            strip_components: 0,
+            token,
+            trash_dir,
+            utc:         false,
             verbosity:   1,
+            yes:         false,
+        }
+    }
+
+    pub fn get_cache(&self) -> bool {
+        self.cache
+    }
+
+    pub fn set_cache(&mut self, cache: bool) {
+        self.cache = cache;
+    }
+
+    pub fn get_cache_dir(&self) -> Result<&Path> {
+        match &self.cache_dir {
+            Some(dir) => Ok(dir),
+            None      => Err(ErrorKind::NoCacheDirGiven)?,
         }
     }
 
+    /// How long, in seconds, cached listings stay fresh.
+    pub fn get_cache_ttl(&self) -> u64 {
+        self.cache_ttl
+    }
+
+    pub fn get_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn get_exclude_patterns(&self) -> &[String] {
+        &self.exclude
+    }
+
+    pub fn add_exclude_pattern(&mut self, pattern: String) {
+        self.exclude.push(pattern);
+    }
+
+    pub fn get_extract(&self) -> bool {
+        self.extract
+    }
+
+    pub fn set_extract(&mut self, extract: bool) {
+        self.extract = extract;
+    }
+
+    pub fn get_force(&self) -> bool {
+        self.force
+    }
+
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    pub fn get_ignore_patterns(&self) -> &[String] {
+        &self.ignore
+    }
+
+    /// Ignore patterns given on the command line, which apply after the
+    /// dotfile’s.
+    pub fn get_ignore_args(&self) -> &[String] {
+        &self.ignore_args
+    }
+
+    pub fn add_ignore_arg(&mut self, pattern: String) {
+        self.ignore_args.push(pattern);
+    }
+
+    pub fn get_ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    pub fn set_ignore_case(&mut self, ignore_case: bool) {
+        self.ignore_case = ignore_case;
+    }
+
+    pub fn get_show_ignored(&self) -> bool {
+        self.show_ignored
+    }
+
+    pub fn set_show_ignored(&mut self, show: bool) {
+        self.show_ignored = show;
+    }
+
+    pub fn get_no_ignore(&self) -> bool {
+        self.no_ignore
+    }
+
+    pub fn set_no_ignore(&mut self, no_ignore: bool) {
+        self.no_ignore = no_ignore;
+    }
+
+    pub fn get_strip_components(&self) -> usize {
+        self.strip_components
+    }
+
+    pub fn set_strip_components(&mut self, strip: usize) {
+        self.strip_components = strip;
+    }
+
+    pub fn get_grading_policy(&self) -> Option<&Path> {
+        self.grading_policy.as_deref()
+    }
+
+    pub fn get_manifest_dir(&self) -> Option<&Path> {
+        self.manifest_dir.as_deref()
+    }
+
+    pub fn get_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
     pub fn get_on_behalf(&self) -> Option<&str> {
         self.on_behalf.as_ref().map(String::as_str)
     }
@@ -79,6 +330,14 @@ impl Config {
         self.on_behalf = Some(username);
     }
 
+    pub fn get_output_format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.format = format;
+    }
+
     pub fn get_overwrite_policy(&self) -> OverwritePolicy {
         self.overwrite
     }
@@ -87,6 +346,49 @@ impl Config {
         self.overwrite = op;
     }
 
+    pub fn get_pager(&self) -> bool {
+        self.pager
+    }
+
+    pub fn set_pager(&mut self, pager: bool) {
+        self.pager = pager;
+    }
+
+    pub fn get_recursive(&self) -> bool {
+        self.recursive
+    }
+
+    pub fn set_recursive(&mut self, recursive: bool) {
+        self.recursive = recursive;
+    }
+
+    pub fn get_regex(&self) -> bool {
+        self.regex
+    }
+
+    pub fn set_regex(&mut self, regex: bool) {
+        self.regex = regex;
+    }
+
+    pub fn get_token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    pub fn get_trash_dir(&self) -> Result<&Path> {
+        match &self.trash_dir {
+            Some(dir) => Ok(dir),
+            None      => Err(ErrorKind::NoTrashDirGiven)?,
+        }
+    }
+
+    pub fn get_yes(&self) -> bool {
+        self.yes
+    }
+
+    pub fn set_yes(&mut self, yes: bool) {
+        self.yes = yes;
+    }
+
     pub fn get_verbosity(&self) -> isize {
         self.verbosity
     }
@@ -105,6 +407,22 @@ impl Config {
         self.verbosity = verbosity;
     }
 
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.color = mode;
+    }
+
+    pub fn activate_color(&self) {
+        style::set_color_mode(self.color);
+    }
+
+    pub fn set_utc(&mut self, utc: bool) {
+        self.utc = utc;
+    }
+
+    pub fn activate_date_display(&self) {
+        messages::set_date_display(self.utc, &self.date_format);
+    }
+
     pub fn get_endpoint(&self) -> &str {
         &self.endpoint
     }
@@ -144,7 +462,9 @@ impl Config {
     }
 
     pub fn load_dotfile(&mut self) -> Result<()> {
-        if let Some(Dotfile {endpoint, verbosity}) = self.read_dotfile()? {
+        if let Some(Dotfile {endpoint, verbosity, token, date_format,
+                             manifest_dir, ignore, cache_ttl, grading_policy})
+                = self.read_dotfile()? {
             if !endpoint.is_empty() {
                 self.endpoint = endpoint;
             }
@@ -152,6 +472,24 @@ impl Config {
             if let Some(i) = verbosity {
                 self.verbosity = i;
             }
+
+            // $GSC_TOKEN takes precedence over the dotfile.
+            if self.token.is_none() {
+                self.token = token;
+            }
+
+            if let Some(format) = date_format {
+                check_date_format(&format)?;
+                self.date_format = format;
+            }
+
+            if let Some(ttl) = cache_ttl {
+                self.cache_ttl = ttl;
+            }
+
+            self.manifest_dir = manifest_dir;
+            self.ignore       = ignore;
+            self.grading_policy = grading_policy;
         }
 
         Ok(())
@@ -159,3 +497,30 @@ impl Config {
 }
 
 
+
+impl std::str::FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json"  => Ok(OutputFormat::Json),
+            "yaml"  => Ok(OutputFormat::Yaml),
+            "csv"   => Ok(OutputFormat::Csv),
+            _       => Err(syntax_error("output format", s))?,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "always" => Ok(ColorMode::Always),
+            "never"  => Ok(ColorMode::Never),
+            "auto"   => Ok(ColorMode::Auto),
+            _        => Err(syntax_error("color mode", s))?,
+        }
+    }
+}
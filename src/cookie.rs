@@ -7,6 +7,15 @@ use std::path::Path;
 
 use super::errors::*;
 
+/// The credentials sent along with a request.
+#[derive(Debug)]
+pub enum Credentials {
+    /// A session cookie, read from the (locked) cookie file.
+    Cookie(CookieFile),
+    /// A static API token, sent as a bearer token.
+    Token(String),
+}
+
 #[derive(Debug)]
 pub struct CookieFile {
     file:       File,
@@ -1,6 +1,8 @@
 use super::RemotePattern;
+use super::spec::RemoteSpec;
 
 use error_chain::*;
+use thousands::Separable;
 use serde_derive::{Serialize, Deserialize};
 
 use std::path::PathBuf;
@@ -20,13 +22,17 @@ pub struct RemoteFiles(pub Vec<String>);
 error_chain! {
     foreign_links {
         Clap(clap::Error);
+        Csv(csv::Error);
         Globset(globset::Error);
         InvalidHeaderValue(reqwest::header::InvalidHeaderValue);
         Io(std::io::Error);
         ParseInt(std::num::ParseIntError);
         ParseFloat(std::num::ParseFloatError);
+        Regex(regex::Error);
         Reqwest(reqwest::Error);
+        SerdeJson(serde_json::Error);
         SerdeYaml(serde_yaml::Error);
+        Zip(zip::result::ZipError);
     }
 
     errors {
@@ -72,15 +78,37 @@ error_chain! {
             display("No remote files matching pattern ‘{}’.", rpat)
         }
 
+        NoMatchingHomework(spec: RemoteSpec) {
+            description("no matching homework")
+            display("No open homeworks match ‘{}’.", spec)
+        }
+
+        DestinationIsMultipleHomeworks(spec: RemoteSpec) {
+            description("destination is multiple homeworks")
+            display("Destination ‘{}’ must name just one homework.", spec)
+        }
+
         CannotCopyLocalToLocal(src: PathBuf, dst: PathBuf) {
             description("cannot copy local to local")
             display("Cannot copy local file ({}) to local destination ({}).",
                     src.display(), dst.display())
         }
 
-        CannotCopyRemoteToRemote(src: RemotePattern, dst: RemotePattern) {
-            description("cannot copy remote to remote")
-            display("Cannot copy remote file ({}) to remote destination ({}).", src, dst)
+        StdinNeedsFileName(hw: usize) {
+            description("stdin needs a remote file name")
+            display("To upload stdin, give a file name, as in ‘hw{}:FILE’.", hw)
+        }
+
+        UnknownArchiveFormat(path: PathBuf) {
+            description("unknown archive format")
+            display("Cannot tell what kind of archive ‘{}’ should be; please give ‘--type’.",
+                    path.display())
+        }
+
+        MixedCopySources(local: PathBuf, remote: RemotePattern) {
+            description("cannot copy local and remote files together")
+            display("Cannot copy local (‘{}’) and remote (‘{}’) files to the server at once.",
+                    local.display(), remote)
         }
 
         BadLocalPath(filename: PathBuf) {
@@ -93,11 +121,47 @@ error_chain! {
             display("Filename not proper UTF-8: ‘{}’.", filename.display())
         }
 
+        DuplicateUploadName(name: String, first: PathBuf, second: PathBuf) {
+            description("duplicate upload name")
+            display("Files ‘{}’ and ‘{}’ would both be uploaded as ‘{}’.",
+                    first.display(), second.display(), name)
+        }
+
+        SourceIsDirectory(path: PathBuf) {
+            description("source is a directory")
+            display("Skipping directory ‘{}’; pass ‘-r’ to upload its contents.",
+                    path.display())
+        }
+
+        CannotConfirm {
+            description("cannot confirm")
+            display("Cannot read an answer from standard input; pass ‘-y’ to go ahead \
+                     without asking.")
+        }
+
+        SourceIsIgnored(path: PathBuf, rule: String) {
+            description("source is ignored")
+            display("Skipping ‘{}’, which is ignored (matches {}); pass ‘--no-ignore’ \
+                     to upload it.", path.display(), rule)
+        }
+
         MultipleSourcesOneDestination {
             description("multiple sources one destination")
             display("Multiple source files cannot be copied to one destination file.")
         }
 
+        DirectoryToDestinationFile(src: PathBuf, dst: RemotePattern) {
+            description("directory to destination file")
+            display("Cannot copy directory ‘{}’ to the single remote file ‘{}’.",
+                    src.display(), dst)
+        }
+
+        ArchiveToDestinationFile(src: PathBuf, dst: RemotePattern) {
+            description("archive to destination file")
+            display("Cannot extract archive ‘{}’ to the single remote file ‘{}’.",
+                    src.display(), dst)
+        }
+
         DestinationPatternIsMultiple(rpat: RemotePattern, rfiles: RemoteFiles) {
             description("destination pattern is multiple")
             display("Destination pattern ‘{}’ resolves to multiple remote files:\n{}", rpat, rfiles)
@@ -124,6 +188,92 @@ error_chain! {
             display("Homework {} does not have item {}.", hw, number)
         }
 
+        NoManifest(hw: usize) {
+            description("no manifest found")
+            display("No manifest found for hw{}; create ‘.gscmanifest’ or pass ‘--manifest’.", hw)
+        }
+
+        ManifestCheckFailed(hw: usize, count: usize) {
+            description("submission does not match manifest")
+            display("Submission hw{} does not match its manifest ({} problem{}).",
+                    hw, count, if *count == 1 { "" } else { "s" })
+        }
+
+        QuotaExceeded(hw: usize, count: usize, uploading: usize, replacing: usize,
+                      used: usize, quota: usize) {
+            description("upload would exceed quota")
+            display("Not enough quota for this upload to hw{}:\n  \
+                     {} bytes in {} file{} to upload\n  \
+                     {} bytes freed by replacing remote files\n  \
+                     {} of {} bytes already used\n  \
+                     {} bytes over quota\n\
+                     Delete some remote files, or pass ‘--force’ to upload anyway.",
+                    hw,
+                    uploading.separate_with_commas(), count, if *count == 1 { "" } else { "s" },
+                    replacing.separate_with_commas(),
+                    used.separate_with_commas(), quota.separate_with_commas(),
+                    (used + uploading).saturating_sub(replacing + quota).separate_with_commas())
+        }
+
+        MoveNeedsOneFile(spec: String, count: usize) {
+            description("move source must be one file")
+            display("‘{}’ matches {} files, but ‘mv’ moves only one at a time.", spec, count)
+        }
+
+        MoveNotVerified(dst: String) {
+            description("moved file does not match original")
+            display("After uploading, ‘{}’ did not match the original.", dst)
+        }
+
+        ChangeWhileOffline {
+            description("cannot change the server while offline")
+            display("Cannot change anything on the server while offline, since ‘--offline’ \
+                     only shows saved listings and files; run again without it.")
+        }
+
+        NotSavedOffline(what: String) {
+            description("nothing saved to use offline")
+            display("There is no saved copy of {} to use offline; \
+                     run the command once while online to save one.", what)
+        }
+
+        GradebookMissingColumn(column: String) {
+            description("gradebook is missing a column")
+            display("The gradebook has no ‘{}’ column.", column)
+        }
+
+        BadGradingPolicy(file: PathBuf, problem: String) {
+            description("bad grading policy")
+            display("Bad grading policy ‘{}’: {}.", file.display(), problem)
+        }
+
+        NoCacheDirGiven {
+            description("no cache directory given")
+            display("Could not find a directory for the cache; please set $GSC_CACHE.")
+        }
+
+        NoTrashDirGiven {
+            description("no trash directory given")
+            display("Could not find a directory for the trash; please set $GSC_TRASH.")
+        }
+
+        NothingInTrash(spec: String) {
+            description("no trashed files match")
+            display("No trashed files match ‘{}’.", spec)
+        }
+
+        SizeMismatch(file: String, got: usize, expected: usize) {
+            description("downloaded file has the wrong size")
+            display("Downloaded {} bytes from ‘{}’, but the server lists it as {} bytes.",
+                    got, file, expected)
+        }
+
+        VerifyFailed(hw: usize, count: usize, total: usize) {
+            description("remote files differ from local files")
+            display("{} of {} file{} in hw{} did not match.",
+                    count, total, if *total == 1 { "" } else { "s" }, hw)
+        }
+
         DestinationFileExists(filename: String) {
             description("destination file exists, and flag ‘-n’ was given")
             display("Not overwriting destination file ‘{}’ (-n).", filename)
@@ -0,0 +1,453 @@
+//! The course gradebook: parsing the server’s grade spreadsheet and
+//! computing final grades under a course policy.
+//!
+//! The spreadsheet has a header row naming its columns. The student’s
+//! username is in a `username` column (or `user`, `netid`, or `name`), and
+//! an optional `section` column gives their section. Columns named like
+//! `hw3` hold homework grades and columns named like `exam2` hold exam
+//! grades; any other column is ignored. A grade is a fraction of full
+//! credit such as `0.85`, a percentage such as `85%`, or points such as
+//! `17/20`, and an empty cell means there is no grade.
+//!
+//! A policy is a YAML file such as:
+//!
+//! ```yaml
+//! homework:
+//!   weight: 60
+//!   drop_lowest: 1
+//! exams:
+//!   weight: 40
+//!   normalize: {mean: 0.8}
+//! letters: {A: 93, A-: 90, B+: 87, B: 83, B-: 80, C: 70, D: 60, F: 0}
+//! ```
+//!
+//! Weights are relative to each other. A missing grade counts as zero, so
+//! it is the first to be dropped, but an assignment that no student has a
+//! grade for yet is left out altogether.
+
+use super::errors::*;
+
+use serde_derive::{Serialize, Deserialize};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const USER_COLUMNS: &[&str] = &["username", "user", "netid", "name"];
+const SECTION_COLUMN: &str  = "section";
+
+const DEFAULT_LETTERS: &[(&str, f64)] = &[
+    ("A", 93.0), ("A-", 90.0),
+    ("B+", 87.0), ("B", 83.0), ("B-", 80.0),
+    ("C+", 77.0), ("C", 73.0), ("C-", 70.0),
+    ("D", 60.0),
+    ("F", 0.0),
+];
+
+/// The grades from the server’s spreadsheet, one row per student.
+#[derive(Debug, Default)]
+pub struct Gradebook {
+    /// The homework numbers, in the order of each student’s `homework`.
+    pub homeworks:  Vec<usize>,
+    /// The exam numbers, in the order of each student’s `exams`.
+    pub exams:      Vec<usize>,
+    pub students:   Vec<Student>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Student {
+    pub username:   String,
+    pub section:    Option<String>,
+    pub homework:   Vec<Option<f64>>,
+    pub exams:      Vec<Option<f64>>,
+}
+
+/// How final grades are computed.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default = "Category::default_weight")]
+    pub homework:   Category,
+    #[serde(default = "Category::default_weight")]
+    pub exams:      Category,
+    /// The lowest final percentage for each letter grade.
+    #[serde(default = "default_letters")]
+    pub letters:    BTreeMap<String, f64>,
+}
+
+/// How one category of grades counts toward the final grade.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Category {
+    pub weight:         f64,
+    /// How many of each student’s lowest grades to leave out.
+    #[serde(default)]
+    pub drop_lowest:    usize,
+    #[serde(default)]
+    pub normalize:      Normalization,
+}
+
+/// How each assignment’s grades are adjusted across the class before
+/// they are totaled.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Grades are used as they are.
+    #[default]
+    None,
+    /// Grades are scaled so that the highest becomes full credit.
+    Max,
+    /// Grades are scaled so that their mean becomes the given fraction.
+    Mean(f64),
+}
+
+/// A student’s computed grades. Grades are fractions of full credit, and
+/// normalized if the policy says so.
+#[derive(Serialize, Debug)]
+pub struct FinalGrade {
+    pub username:       String,
+    pub section:        Option<String>,
+    pub homework:       Vec<Option<f64>>,
+    pub exams:          Vec<Option<f64>>,
+    pub homework_total: Option<f64>,
+    pub exam_total:     Option<f64>,
+    pub percent:        f64,
+    pub letter:         String,
+}
+
+/// The final grades for (some of) the class.
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub homeworks:  Vec<usize>,
+    pub exams:      Vec<usize>,
+    pub students:   Vec<FinalGrade>,
+}
+
+// What a spreadsheet column holds. The student’s username is in exactly
+// one column, so `classify_column` doesn’t look for it.
+enum Column {
+    User,
+    Section,
+    Homework(usize),
+    Exam(usize),
+    Other,
+}
+
+impl Gradebook {
+    /// Parses the spreadsheet.
+    pub fn parse<R: Read>(input: R) -> Result<Self> {
+        let mut reader  = csv::Reader::from_reader(input);
+        let headers     = reader.headers()?.clone();
+        let mut columns: Vec<Column> = headers.iter().map(classify_column).collect();
+
+        // The first of `USER_COLUMNS` present names the student.
+        let user = USER_COLUMNS.iter()
+            .find_map(|name| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name)))
+            .ok_or_else(|| ErrorKind::GradebookMissingColumn(USER_COLUMNS[0].to_owned()))?;
+        columns[user] = Column::User;
+
+        let mut result  = Gradebook::default();
+
+        for column in &columns {
+            match column {
+                Column::Homework(n) => result.homeworks.push(*n),
+                Column::Exam(n)     => result.exams.push(*n),
+                _                   => (),
+            }
+        }
+
+        for (i, record) in reader.records().enumerate() {
+            let record  = record?;
+            let mut student = Student {
+                username:   String::new(),
+                section:    None,
+                homework:   Vec::new(),
+                exams:      Vec::new(),
+            };
+
+            for (column, cell) in columns.iter().zip(record.iter()) {
+                let cell = cell.trim();
+
+                match column {
+                    Column::User        => student.username = cell.to_owned(),
+                    Column::Section     => student.section =
+                        Some(cell.to_owned()).filter(|s| !s.is_empty()),
+                    Column::Homework(_) => student.homework.push(parse_grade(cell)
+                        .chain_err(|| format!("In row {} of the gradebook", i + 2))?),
+                    Column::Exam(_)     => student.exams.push(parse_grade(cell)
+                        .chain_err(|| format!("In row {} of the gradebook", i + 2))?),
+                    Column::Other       => (),
+                }
+            }
+
+            student.homework.resize(result.homeworks.len(), None);
+            student.exams.resize(result.exams.len(), None);
+
+            if !student.username.is_empty() {
+                result.students.push(student);
+            }
+        }
+
+        result.drop_ungraded();
+        Ok(result)
+    }
+
+    // Removes the assignments that no student has a grade for.
+    fn drop_ungraded(&mut self) {
+        let homework = graded_columns(self.students.iter().map(|s| &s.homework),
+                                      self.homeworks.len());
+        let exams    = graded_columns(self.students.iter().map(|s| &s.exams),
+                                      self.exams.len());
+
+        retain_columns(&mut self.homeworks, &homework);
+        retain_columns(&mut self.exams, &exams);
+
+        for student in &mut self.students {
+            retain_columns(&mut student.homework, &homework);
+            retain_columns(&mut student.exams, &exams);
+        }
+    }
+
+    /// Computes every student’s final grade. Normalization considers the
+    /// whole class, so filter the report rather than the gradebook.
+    pub fn grade(&self, policy: &Policy) -> Report {
+        let homework = normalize(self.students.iter().map(|s| &s.homework),
+                                 self.homeworks.len(), policy.homework.normalize);
+        let exams    = normalize(self.students.iter().map(|s| &s.exams),
+                                 self.exams.len(), policy.exams.normalize);
+        let letters  = policy.letter_cutoffs();
+
+        let students = self.students.iter().zip(homework).zip(exams)
+            .map(|((student, homework), exams)| {
+                let homework_total = policy.homework.total(&homework);
+                let exam_total     = policy.exams.total(&exams);
+
+                let weighted = [(homework_total, policy.homework.weight),
+                                (exam_total, policy.exams.weight)];
+                let weight: f64 = weighted.iter()
+                    .filter(|(total, _)| total.is_some())
+                    .map(|(_, weight)| weight)
+                    .sum();
+                let points: f64 = weighted.iter()
+                    .filter_map(|(total, weight)| total.map(|t| t * weight))
+                    .sum();
+                let percent = if weight > 0.0 { 100.0 * points / weight } else { 0.0 };
+
+                FinalGrade {
+                    username:   student.username.clone(),
+                    section:    student.section.clone(),
+                    homework,
+                    exams,
+                    homework_total,
+                    exam_total,
+                    percent,
+                    letter:     letter_for(&letters, percent),
+                }
+            })
+            .collect();
+
+        Report {
+            homeworks: self.homeworks.clone(),
+            exams:     self.exams.clone(),
+            students,
+        }
+    }
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("Could not read grading policy: {}", path.display()))?;
+        let parsed: Policy = serde_yaml::from_str(&contents)
+            .chain_err(|| format!("Could not parse grading policy: {}", path.display()))?;
+
+        if let Err(problem) = parsed.check() {
+            Err(ErrorKind::BadGradingPolicy(path.to_owned(), problem))?;
+        }
+
+        Ok(parsed)
+    }
+
+    fn check(&self) -> std::result::Result<(), String> {
+        for (name, category) in &[("homework", &self.homework), ("exams", &self.exams)] {
+            if category.weight.is_nan() || category.weight < 0.0 {
+                Err(format!("{} has weight {}, but weights can’t be negative",
+                            name, category.weight))?;
+            }
+
+            if let Normalization::Mean(mean) = category.normalize {
+                if mean.is_nan() || mean <= 0.0 {
+                    Err(format!("{} are normalized to mean {}, which isn’t positive",
+                                name, mean))?;
+                }
+            }
+        }
+
+        if self.homework.weight + self.exams.weight == 0.0 {
+            Err("homework and exams both have weight 0".to_owned())?;
+        }
+
+        if self.letters.is_empty() {
+            Err("there are no letter grades".to_owned())?;
+        }
+
+        Ok(())
+    }
+
+    // The letters, highest cutoff first.
+    fn letter_cutoffs(&self) -> Vec<(&str, f64)> {
+        let mut result: Vec<_> = self.letters.iter()
+            .map(|(letter, cutoff)| (letter.as_str(), *cutoff))
+            .collect();
+        result.sort_by(|a, b| b.1.total_cmp(&a.1));
+        result
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            homework:   Category::default_weight(),
+            exams:      Category::default_weight(),
+            letters:    default_letters(),
+        }
+    }
+}
+
+impl Category {
+    fn default_weight() -> Self {
+        Category {
+            weight:         1.0,
+            drop_lowest:    0,
+            normalize:      Normalization::None,
+        }
+    }
+
+    // The mean of the grades that aren’t dropped, with missing grades as
+    // zero, or `None` if there are no grades to count.
+    fn total(&self, grades: &[Option<f64>]) -> Option<f64> {
+        let mut grades: Vec<f64> = grades.iter().map(|g| g.unwrap_or(0.0)).collect();
+        grades.sort_by(f64::total_cmp);
+
+        let kept = &grades[self.drop_lowest.min(grades.len()) ..];
+
+        if kept.is_empty() {
+            None
+        } else {
+            Some(kept.iter().sum::<f64>() / kept.len() as f64)
+        }
+    }
+}
+
+fn default_letters() -> BTreeMap<String, f64> {
+    DEFAULT_LETTERS.iter()
+        .map(|(letter, cutoff)| (letter.to_string(), *cutoff))
+        .collect()
+}
+
+fn classify_column(name: &str) -> Column {
+    let name = name.trim().to_lowercase();
+
+    if name == SECTION_COLUMN {
+        Column::Section
+    } else if let Some(n) = numbered_column(&name, &["hw", "homework"]) {
+        Column::Homework(n)
+    } else if let Some(n) = numbered_column(&name, &["ex", "exam"]) {
+        Column::Exam(n)
+    } else {
+        Column::Other
+    }
+}
+
+// The number in a column name such as `hw3` or `exam 2`.
+fn numbered_column(name: &str, prefixes: &[&str]) -> Option<usize> {
+    prefixes.iter()
+        .filter_map(|prefix| name.strip_prefix(prefix))
+        .find_map(|rest| rest.trim_start().parse().ok())
+}
+
+fn parse_grade(cell: &str) -> Result<Option<f64>> {
+    if cell.is_empty() {
+        return Ok(None);
+    }
+
+    let parsed = if let Some(percent) = cell.strip_suffix('%') {
+        percent.trim().parse::<f64>().ok().map(|p| p / 100.0)
+    } else if let Some((points, possible)) = cell.split_once('/') {
+        match (points.trim().parse::<f64>(), possible.trim().parse::<f64>()) {
+            (Ok(points), Ok(possible)) if possible > 0.0 => Some(points / possible),
+            _                                            => None,
+        }
+    } else {
+        cell.parse().ok()
+    };
+
+    match parsed.filter(|grade: &f64| grade.is_finite()) {
+        Some(grade) => Ok(Some(grade)),
+        None        => Err(syntax_error("grade", cell))?,
+    }
+}
+
+// Which of the `count` assignments anyone has a grade for.
+fn graded_columns<'a, I>(grades: I, count: usize) -> Vec<bool>
+    where I: Iterator<Item = &'a Vec<Option<f64>>> {
+
+    let mut result = vec![false; count];
+
+    for grades in grades {
+        for (graded, grade) in result.iter_mut().zip(grades) {
+            *graded |= grade.is_some();
+        }
+    }
+
+    result
+}
+
+// Keeps the elements of `row` whose place in `keep` is true.
+fn retain_columns<T>(row: &mut Vec<T>, keep: &[bool]) {
+    let mut keep = keep.iter();
+    row.retain(|_| keep.next() == Some(&true));
+}
+
+// Applies `normalization` to each of the `count` assignments, across all
+// the students’ grades.
+fn normalize<'a, I>(grades: I, count: usize, normalization: Normalization)
+    -> Vec<Vec<Option<f64>>>
+    where I: Iterator<Item = &'a Vec<Option<f64>>> {
+
+    let mut result: Vec<Vec<Option<f64>>> = grades.cloned().collect();
+
+    for i in 0 .. count {
+        let column: Vec<f64> = result.iter().filter_map(|grades| grades[i]).collect();
+        if column.is_empty() { continue; }
+
+        let scale = match normalization {
+            Normalization::None       => continue,
+            Normalization::Max        => {
+                let max = column.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                if max > 0.0 { 1.0 / max } else { continue }
+            }
+            Normalization::Mean(mean) => {
+                let actual = column.iter().sum::<f64>() / column.len() as f64;
+                if actual > 0.0 { mean / actual } else { continue }
+            }
+        };
+
+        for grades in &mut result {
+            if let Some(grade) = &mut grades[i] {
+                *grade *= scale;
+            }
+        }
+    }
+
+    result
+}
+
+fn letter_for(cutoffs: &[(&str, f64)], percent: f64) -> String {
+    cutoffs.iter()
+        .find(|(_, cutoff)| percent >= *cutoff)
+        .or_else(|| cutoffs.last())
+        .map(|(letter, _)| letter.to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,216 @@
+//! Ignore files, which keep local files from being uploaded.
+//!
+//! A `.gscignore` file follows the rules of `.gitignore`: each line is a
+//! wildcard pattern, `#` starts a comment, `!` re-includes files that an
+//! earlier pattern excluded, a trailing `/` matches only directories, and
+//! a pattern containing any other `/` is relative to the directory holding
+//! the ignore file (rather than matching a name at any depth). Later
+//! patterns, and patterns in deeper directories, take precedence. Nothing
+//! within an ignored directory can be re-included.
+
+use super::errors::*;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// The name of an ignore file.
+pub const IGNORE_NAME: &str = ".gscignore";
+
+/// One pattern from an ignore file or the dotfile.
+#[derive(Debug)]
+pub struct Rule {
+    pattern:    String,
+    origin:     String,
+    matcher:    globset::GlobMatcher,
+    negated:    bool,
+    dir_only:   bool,
+    anchored:   bool,
+}
+
+/// Decides which files under a root directory are ignored, loading the
+/// ignore files it needs as it goes.
+#[derive(Debug)]
+pub struct Ignorer {
+    root:       PathBuf,
+    global:     Rc<Vec<Rule>>,
+    loaded:     HashMap<PathBuf, Rc<Vec<Rule>>>,
+}
+
+impl Rule {
+    /// Parses one line of an ignore file, returning `None` for blank lines
+    /// and comments. `origin` says where the line came from, for messages.
+    pub fn parse(line: &str, origin: String) -> Result<Option<Self>> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let line = if line.ends_with("\\ ") { line } else { line.trim_end() };
+
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (negated, rest) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None       => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+
+        let (dir_only, rest) = match rest.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None       => (false, rest),
+        };
+
+        let anchored = rest.contains('/');
+        let glob     = rest.strip_prefix('/').unwrap_or(rest);
+
+        let matcher  = globset::GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()
+            .chain_err(|| format!("Bad ignore pattern ‘{}’ ({})", line, origin))?
+            .compile_matcher();
+
+        Ok(Some(Rule {
+            pattern:    line.to_owned(),
+            origin,
+            matcher,
+            negated,
+            dir_only,
+            anchored,
+        }))
+    }
+
+    // `path` is relative to the directory the rule came from.
+    fn is_match(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        if self.anchored {
+            self.matcher.is_match(path)
+        } else {
+            path.file_name().is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "‘{}’ from {}", self.pattern, self.origin)
+    }
+}
+
+/// Parses the lines of an ignore file.
+pub fn parse_rules<'a, I>(lines: I, origin: &str) -> Result<Vec<Rule>>
+    where I: IntoIterator<Item = &'a str> {
+
+    let mut rules = Vec::new();
+
+    for (i, line) in lines.into_iter().enumerate() {
+        if let Some(rule) = Rule::parse(line, format!("{}:{}", origin, i + 1))? {
+            rules.push(rule);
+        }
+    }
+
+    Ok(rules)
+}
+
+impl Ignorer {
+    /// Ignores files under `root` according to the ignore files in `root`
+    /// and its subdirectories, along with the `global` rules, which act as
+    /// though they came before everything in `root`’s ignore file.
+    pub fn new(root: &Path, global: Vec<Rule>) -> Result<Self> {
+        Ok(Ignorer {
+            root:   fs::canonicalize(root)?,
+            global: Rc::new(global),
+            loaded: HashMap::new(),
+        })
+    }
+
+    /// Describes the rule that excludes `path`, if any. Files outside the
+    /// root are subject only to the global rules and their own directory’s
+    /// ignore file.
+    pub fn check(&mut self, path: &Path, is_dir: bool) -> Result<Option<String>> {
+        let path = absolute(path)?;
+        let base = if path.starts_with(&self.root) {
+            self.root.clone()
+        } else {
+            path.parent().unwrap_or(&path).to_owned()
+        };
+        let rel  = path.strip_prefix(&base).unwrap_or(&path);
+
+        let components: Vec<Component> = rel.components().collect();
+        let mut prefix = PathBuf::new();
+
+        // Checking each ancestor first means that nothing within an ignored
+        // directory can be re-included.
+        for (i, component) in components.iter().enumerate() {
+            prefix.push(component);
+            let is_last = i + 1 == components.len();
+
+            if let Some(rule) = self.check_one(&base, &prefix, is_dir || !is_last)? {
+                return Ok(Some(rule));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Checks `rel`, relative to `base`, without regard to its ancestors.
+    fn check_one(&mut self, base: &Path, rel: &Path, is_dir: bool) -> Result<Option<String>> {
+        let mut layers = vec![
+            (PathBuf::new(), Rc::clone(&self.global)),
+            (PathBuf::new(), self.load(base)?),
+        ];
+
+        let mut dir = PathBuf::new();
+        for component in rel.parent().into_iter().flat_map(Path::components) {
+            dir.push(component);
+            layers.push((dir.clone(), self.load(&base.join(&dir))?));
+        }
+
+        let mut result = None;
+
+        for (dir, rules) in &layers {
+            let sub = rel.strip_prefix(dir).unwrap_or(rel);
+
+            for rule in rules.iter().filter(|rule| rule.is_match(sub, is_dir)) {
+                result = if rule.negated { None } else { Some(rule.to_string()) };
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn load(&mut self, dir: &Path) -> Result<Rc<Vec<Rule>>> {
+        if let Some(rules) = self.loaded.get(dir) {
+            return Ok(Rc::clone(rules));
+        }
+
+        let filename = dir.join(IGNORE_NAME);
+        let rules    = match fs::read_to_string(&filename) {
+            Ok(contents) => parse_rules(contents.lines(), &filename.display().to_string())?,
+            Err(error)   => match error.kind() {
+                io::ErrorKind::NotFound => Vec::new(),
+                _                       => Err(error)
+                    .chain_err(|| format!("Could not read {}", filename.display()))?,
+            }
+        };
+
+        let rules = Rc::new(rules);
+        self.loaded.insert(dir.to_owned(), Rc::clone(&rules));
+        Ok(rules)
+    }
+}
+
+// Makes `path` absolute without resolving a symbolic link in its last
+// component, so that a link is judged by its own name.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            Ok(fs::canonicalize(parent)?.join(name))
+        }
+        _                          => Ok(fs::canonicalize(path)?),
+    }
+}
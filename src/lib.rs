@@ -1,27 +1,44 @@
-#![recursion_limit = "128"]
+#![recursion_limit = "256"]
 
 use vlog::*;
+use reqwest::header::HeaderValue;
 use percent_encoding::{utf8_percent_encode, define_encode_set};
+use sha2::{Digest, Sha256};
 use thousands::Separable;
 
 use std::cell::{Cell, RefCell};
 use std::collections::{hash_map, HashMap};
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 
+pub mod archive;
+pub mod cache;
+pub mod calendar;
 pub mod cookie;
 pub mod config;
 pub mod errors;
+pub mod gradebook;
+pub mod ignore;
+pub mod manifest;
 pub mod messages;
+pub mod output;
+pub mod pager;
+pub mod spec;
+pub mod style;
+pub mod trash;
 
 use self::errors::*;
 use self::cookie::*;
+use self::style::Style;
 
 pub struct GscClient {
     http:               reqwest::Client,
     config:             config::Config,
     submission_uris:    RefCell<HashMap<String, Vec<Option<String>>>>,
+    whoami:             RefCell<Option<String>>,
     had_warning:        Cell<bool>,
+    ignored_count:      Cell<usize>,
+    fresh_listings:     Cell<bool>,
 }
 
 #[derive(Clone, Debug)]
@@ -30,9 +47,56 @@ pub struct RemotePattern {
     pub pat:    String,
 }
 
+// A local file to upload, along with its remote name.
+struct LocalFile {
+    path:   PathBuf,
+    name:   String,
+}
+
+/// How `ls` orders and presents files.
+#[derive(Clone, Debug, Default)]
+pub struct LsOptions {
+    pub sort:       LsSort,
+    pub reverse:    bool,
+    /// If non-empty, only files with these purposes are listed.
+    pub purposes:   Vec<messages::FilePurpose>,
+    pub long:       bool,
+}
+
+/// Which students `admin gradebook` reports on. Empty lists mean everyone.
+#[derive(Clone, Debug, Default)]
+pub struct GradebookFilter {
+    pub sections:   Vec<String>,
+    pub users:      Vec<String>,
+}
+
+/// How `grep` matches lines and reports matches.
+#[derive(Clone, Debug, Default)]
+pub struct GrepOptions {
+    pub ignore_case:    bool,
+    pub line_numbers:   bool,
+    /// Print only the names of files with matches.
+    pub files_only:     bool,
+    /// Print only the number of matching lines in each file.
+    pub count:          bool,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LsSort {
+    /// The order the server gives.
+    #[default]
+    Server,
+    /// Newest first.
+    Time,
+    /// Largest first.
+    Size,
+}
+
 pub enum CpArg {
     Local(PathBuf),
     Remote(RemotePattern),
+    /// Stdin as a source, or stdout as a destination.
+    Stdio,
 }
 
 impl GscClient {
@@ -44,7 +108,10 @@ impl GscClient {
             http:               reqwest::Client::new(),
             config,
             submission_uris:    RefCell::new(HashMap::new()),
+            whoami:             RefCell::new(None),
             had_warning:        Cell::new(false),
+            ignored_count:      Cell::new(0),
+            fresh_listings:     Cell::new(false),
         })
     }
 
@@ -60,6 +127,71 @@ impl GscClient {
         self.had_warning.get()
     }
 
+    /// Expands each spec into a pattern for every homework it names. A spec
+    /// naming one homework is taken as is; other sets are matched against
+    /// the user’s submissions, skipping those that haven’t opened yet.
+    pub fn expand_specs(&self, specs: &[spec::RemoteSpec]) -> Result<Vec<RemotePattern>> {
+        let mut result   = Vec::new();
+        let mut open_hws = None;
+
+        for spec in specs {
+            if let Some(hw) = spec.hws.single() {
+                result.push(spec.for_hw(hw));
+                continue;
+            }
+
+            if open_hws.is_none() {
+                open_hws = Some(self.fetch_open_hws()?);
+            }
+
+            let before = result.len();
+            result.extend(open_hws.iter().flatten()
+                .filter(|&&hw| spec.hws.contains(hw))
+                .map(|&hw| spec.for_hw(hw)));
+
+            if result.len() == before {
+                Err(ErrorKind::NoMatchingHomework(spec.clone()))?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn expand_cp_specs(&self, specs: &[spec::CpSpec]) -> Result<Vec<CpArg>> {
+        let mut result = Vec::new();
+
+        for spec in specs {
+            match spec {
+                spec::CpSpec::Local(path)   => result.push(CpArg::Local(path.clone())),
+                spec::CpSpec::Remote(rspec) =>
+                    result.extend(self.expand_specs(std::slice::from_ref(rspec))?
+                        .into_iter()
+                        .map(CpArg::Remote)),
+                spec::CpSpec::Stdio         => result.push(CpArg::Stdio),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Like [`GscClient::expand_cp_specs`], but a destination must name
+    /// exactly one homework.
+    pub fn expand_cp_dst(&self, spec: &spec::CpSpec) -> Result<CpArg> {
+        if let Some(arg) = spec.to_single() {
+            return Ok(arg);
+        }
+
+        let mut args = self.expand_cp_specs(std::slice::from_ref(spec))?;
+
+        match (args.pop(), spec) {
+            (Some(arg), _) if args.is_empty()  => Ok(arg),
+            (_, spec::CpSpec::Remote(rspec))   =>
+                Err(ErrorKind::DestinationIsMultipleHomeworks(rspec.clone()))?,
+            (_, spec::CpSpec::Local(_))        |
+            (_, spec::CpSpec::Stdio)           => unreachable!("local specs are single"),
+        }
+    }
+
     pub fn admin_csv(&self) -> Result<()> {
         let uri          = format!("{}/api/grades.csv", self.config.get_endpoint());
         let request      = self.http.get(&uri);
@@ -68,8 +200,84 @@ impl GscClient {
         Ok(())
     }
 
+    pub fn admin_gradebook(&self, policy_file: Option<&Path>, filter: &GradebookFilter)
+        -> Result<()> {
+
+        let policy = match policy_file.or_else(|| self.config.get_grading_policy()) {
+            Some(filename) => {
+                v3!("Using grading policy ‘{}’...", filename.display());
+                gradebook::Policy::load(filename)?
+            }
+            None           => gradebook::Policy::default(),
+        };
+
+        let uri          = format!("{}/api/grades.csv", self.config.get_endpoint());
+        let request      = self.http.get(&uri);
+        let response     = self.send_request(request)?;
+        let gradebook    = gradebook::Gradebook::parse(response)?;
+
+        let mut report   = gradebook.grade(&policy);
+
+        for section in &filter.sections {
+            if !report.students.iter().any(|student| student.section.as_ref() == Some(section)) {
+                self.warn(format!("No students are in section ‘{}’.", section));
+            }
+        }
+
+        for user in &filter.users {
+            if !report.students.iter().any(|student| &student.username == user) {
+                self.warn(format!("No student ‘{}’ is in the gradebook.", user));
+            }
+        }
+
+        report.students.retain(|student| {
+            (filter.sections.is_empty() || student.section.as_ref()
+                .is_some_and(|section| filter.sections.contains(section)))
+            && (filter.users.is_empty() || filter.users.contains(&student.username))
+        });
+
+        if self.print_listing(&report)? {
+            return Ok(());
+        }
+
+        let grades       = report.homeworks.len() + report.exams.len();
+        let mut table    = tabular::Table::new(
+            &format!("{{:<}}  {{:<}}{}  {{:>}}  {{:>}}  {{:>}}  {{:<}}", "  {:>}".repeat(grades)));
+
+        let mut heading  = tabular::Row::new().with_cell("user").with_cell("section");
+        for hw in &report.homeworks {
+            heading.add_cell(format!("hw{}", hw));
+        }
+        for exam in &report.exams {
+            heading.add_cell(format!("exam{}", exam));
+        }
+        table.add_row(heading.with_cell("hw").with_cell("exam").with_cell("final")
+                             .with_cell("grade"));
+
+        let show = |grade: Option<f64>| grade.map_or_else(String::new,
+                                                          |g| format!("{:.1}", 100.0 * g));
+
+        for student in &report.students {
+            let mut row = tabular::Row::new()
+                .with_cell(&student.username)
+                .with_cell(student.section.as_deref().unwrap_or(""));
+            for grade in student.homework.iter().chain(&student.exams) {
+                row.add_cell(show(*grade));
+            }
+            table.add_row(row
+                .with_cell(show(student.homework_total))
+                .with_cell(show(student.exam_total))
+                .with_ansi_cell(style::paint(Style::Grade(student.percent / 100.0),
+                                             format!("{:.1}%", student.percent)))
+                .with_cell(&student.letter));
+        }
+
+        v1!("{}", table);
+        Ok(())
+    }
+
     pub fn admin_divorce(&self, username: &str, hw: usize) -> Result<()> {
-        let cookie      = self.load_cookie_file()?;
+        let cookie      = self.load_auth()?;
         let uri         = self.get_uri_for_submission(username, hw, cookie)?;
         let mut message = messages::SubmissionChange::default();
         message.owner2  = Some(());
@@ -81,7 +289,7 @@ impl GscClient {
     pub fn admin_extend(&self, username: &str, hw: usize, datetime: &str, eval: bool)
         -> Result<()> {
 
-        let cookie       = self.load_cookie_file()?;
+        let cookie       = self.load_auth()?;
         let uri          = self.get_uri_for_submission(username, hw, cookie)?;
         let mut message  = messages::SubmissionChange::default();
         if eval {
@@ -95,12 +303,19 @@ impl GscClient {
     }
 
     pub fn admin_partners(&self, username: &str, hw: usize) -> Result<()> {
-        let cookie       = self.load_cookie_file()?;
+        let cookie       = self.load_auth()?;
         let uri          = self.get_uri_for_submission(username, hw, cookie)?;
         let request      = self.http.get(&uri);
         let mut response = self.send_request(request)?;
         let submission: messages::Submission = response.json()?;
 
+        if self.print_listing(&output::Owners {
+            owner1: submission.owner1.name.clone(),
+            owner2: submission.owner2.as_ref().map(|o| o.name.clone()),
+        })? {
+            return Ok(());
+        }
+
         let mut buf      = submission.owner1.name.clone();
         if let Some(owner2) = &submission.owner2 {
             buf.push(' ');
@@ -115,7 +330,7 @@ impl GscClient {
     fn get_evals(&self, username: &str, hw: usize)
         -> Result<Vec<messages::EvalShort>> {
 
-        let cookie       = self.load_cookie_file()?;
+        let cookie       = self.load_auth()?;
         let uri          = self.get_uri_for_submission(username, hw, cookie)?;
         let request      = self.http.get(&uri);
         let mut response = self.send_request(request)?;
@@ -201,6 +416,10 @@ impl GscClient {
         let mut result  = self.send_request(request)?;
         let submissions: Vec<messages::SubmissionShort> = result.json()?;
 
+        if self.print_listing(&submissions)? {
+            return Ok(());
+        }
+
         let mut table = tabular::Table::new(" {:>}  {:<}  {:<}");
 
         for submission in &submissions {
@@ -215,7 +434,57 @@ impl GscClient {
         Ok(())
     }
 
+    /// Writes every file in `hw` to an archive at `dst`, or to stdout if
+    /// `dst` is `-`.
+    pub fn archive(&self, hw: usize, dst: &Path, format: archive::ArchiveFormat) -> Result<()> {
+        let rpat  = RemotePattern { hw, pat: String::new() };
+        let files = self.fetch_nonempty_file_list(&rpat)?;
+
+        if dst == Path::new("-") {
+            let stdout = io::stdout();
+            return self.write_archive(hw, &files, archive::Archive::new(format, stdout.lock()));
+        }
+
+        if !self.is_okay_to_write(&mut self.config.get_overwrite_policy(), dst)? {
+            return Ok(());
+        }
+
+        let file   = std::fs::File::create(dst)?;
+        let result = self.write_archive(hw, &files,
+                                        archive::Archive::new(format, io::BufWriter::new(file)));
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(dst);
+        }
+
+        result?;
+        v2!("Done.");
+        Ok(())
+    }
+
+    fn write_archive<W: Write>(&self,
+                               hw: usize,
+                               files: &[messages::FileMeta],
+                               mut archive: archive::Archive<W>)
+        -> Result<()> {
+
+        for meta in files {
+            let path = match meta.purpose.to_dir() {
+                "." => format!("hw{}/{}", hw, meta.name),
+                dir => format!("hw{}/{}/{}", hw, dir, meta.name),
+            };
+
+            ve2!("Archiving ‘hw{}:{}’ -> ‘{}’...", hw, meta.name, path);
+            let contents = self.fetch_file_contents(hw, meta)?;
+            archive.append(&path, &meta.upload_time, &contents)?;
+        }
+
+        archive.finish()
+    }
+
     pub fn auth(&mut self, username: &str) -> Result<()> {
+        self.check_online()?;
+
         let uri = self.user_uri(username);
 
         let cookie_file = self.config.get_cookie_file()?;
@@ -228,7 +497,7 @@ impl GscClient {
                 .send()?;
 
             let cookie_lock = CookieFile::new(cookie_file, username)?;
-            match self.handle_response(&mut response, cookie_lock) {
+            match self.handle_response(&mut response, Credentials::Cookie(cookie_lock)) {
                 Ok(()) => {
                     v2!("Authenticated as {}", username);
                     return Ok(());
@@ -241,11 +510,103 @@ impl GscClient {
         }
     }
 
+    pub fn check(&self, hw: usize, manifest_file: Option<&Path>) -> Result<()> {
+        let manifest_file = match manifest_file {
+            Some(filename) => filename.to_owned(),
+            None           => manifest::Manifest::find(hw, self.config.get_manifest_dir())?
+                .ok_or(ErrorKind::NoManifest(hw))?,
+        };
+
+        v3!("Checking hw{} against manifest ‘{}’...", hw, manifest_file.display());
+        let manifest = manifest::Manifest::load(&manifest_file)?;
+        let files    = self.fetch_all_files(hw)?;
+        let problems = manifest.check(&files)?;
+
+        for problem in &problems {
+            self.warn(problem);
+        }
+
+        if problems.is_empty() {
+            v2!("hw{} matches its manifest.", hw);
+            Ok(())
+        } else {
+            Err(ErrorKind::ManifestCheckFailed(hw, problems.len()))?
+        }
+    }
+
     pub fn cp(&self, srcs: &[CpArg], dst: &CpArg) -> Result<()> {
         match dst {
             CpArg::Local(filename) => self.cp_dn(srcs, filename),
-            CpArg::Remote(rpat)    => self.cp_up(srcs, rpat),
+            CpArg::Remote(rpat)    => {
+                self.check_online()?;
+                self.cp_up(srcs, rpat)
+            }
+            CpArg::Stdio           => self.cp_stdout(srcs),
+        }
+    }
+
+    // Writes one remote file to stdout.
+    fn cp_stdout(&self, srcs: &[CpArg]) -> Result<()> {
+        let rpat = match srcs {
+            [CpArg::Remote(rpat)] => rpat,
+            [CpArg::Local(path)]  =>
+                Err(ErrorKind::CannotCopyLocalToLocal(path.clone(), "-".into()))?,
+            [CpArg::Stdio]        =>
+                Err(ErrorKind::CannotCopyLocalToLocal("-".into(), "-".into()))?,
+            _                     => Err(ErrorKind::MultipleSourcesOneDestination)?,
+        };
+
+        if rpat.is_whole_hw() {
+            Err(ErrorKind::SourceHwToDestinationFile(rpat.hw, "-".into()))?;
+        }
+
+        let meta     = self.fetch_one_filename(rpat)?;
+        ve2!("Downloading ‘hw{}:{}’ -> stdout...", rpat.hw, meta.name);
+        let contents = self.fetch_file_contents(rpat.hw, &meta)?;
+
+        let stdout   = io::stdout();
+        let mut out  = stdout.lock();
+        out.write_all(&contents)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    // Uploads all of stdin as one remote file. The server needs to know the
+    // length up front, so stdin is read in full first.
+    fn cp_stdin(&self, dst: &RemotePattern) -> Result<()> {
+        if dst.is_whole_hw() {
+            Err(ErrorKind::StdinNeedsFileName(dst.hw))?;
         }
+
+        let dsts = self.fetch_dest_file_list(dst)?;
+        let dst  = match dsts.len() {
+            0 => dst.clone(),
+            1 => dst.with_pat(&dsts[0].name),
+            _ => Err(dest_pat_is_multiple(dst, &dsts))?,
+        };
+
+        let mut contents = Vec::new();
+        io::stdin().lock().read_to_end(&mut contents)?;
+        let byte_count   = contents.len();
+
+        if !self.config.get_force() {
+            self.check_quota(dst.hw, &[(&dst.pat, byte_count)])?;
+        }
+
+        v2!("Uploading stdin -> ‘{}’...", dst);
+        self.upload_body(contents, &dst)?;
+
+        match self.fetch_all_files(dst.hw)?.iter().find(|meta| meta.name == dst.pat) {
+            Some(meta) if meta.byte_count == byte_count => (),
+            Some(meta) => self.warn(format!("Uploaded {} bytes from stdin, but the server \
+                                             has {} bytes in ‘{}’.",
+                                            byte_count, meta.byte_count, dst)),
+            None       => self.warn(format!("Uploaded stdin, but ‘{}’ is not on the server.",
+                                            dst)),
+        }
+
+        v2!("Done.");
+        Ok(())
     }
 
     fn cp_dn(&self, raw_srcs: &[CpArg], dst: &Path) -> Result<()> {
@@ -257,6 +618,8 @@ impl GscClient {
                     Err(ErrorKind::CannotCopyLocalToLocal(filename.clone(), dst.to_owned()))?,
                 CpArg::Remote(rpat)    =>
                     src_rpats.push(rpat),
+                CpArg::Stdio           =>
+                    Err(ErrorKind::CannotCopyLocalToLocal("-".into(), dst.to_owned()))?,
             }
         }
 
@@ -344,17 +707,21 @@ impl GscClient {
     }
 
     fn download_file(&self, hw: usize, meta: &messages::FileMeta, dst: &Path) -> Result<()> {
+        ve2!("Downloading ‘hw{}:{}’ -> ‘{}’...", hw, meta.name, dst.display());
+        let mut contents = self.open_raw_contents(meta)?;
+
         let mut file = std::fs::OpenOptions::new()
             .write(true)
             .create(true)
             .truncate(true)
             .open(dst)?;
+        let byte_count   = io::copy(&mut contents, &mut file)? as usize;
 
-        let uri          = format!("{}{}", self.config.get_endpoint(), meta.uri);
-        let request      = self.http.get(&uri);
-        ve2!("Downloading ‘hw{}:{}’ -> ‘{}’...", hw, meta.name, dst.display());
-        let mut response = self.send_request(request)?;
-        response.copy_to(&mut file)?;
+        if byte_count != meta.byte_count {
+            self.warn(format!("Downloaded {} bytes from ‘hw{}:{}’, but the server \
+                               lists it as {} bytes.",
+                              byte_count, hw, meta.name, meta.byte_count));
+        }
 
         Ok(())
     }
@@ -379,19 +746,60 @@ impl GscClient {
     }
 
     fn cp_up(&self, raw_srcs: &[CpArg], dst: &RemotePattern) -> Result<()> {
-        let mut srcs = Vec::new();
+        let mut srcs        = Vec::new();
+        let mut remote_srcs = Vec::new();
 
         for src in raw_srcs {
             match src {
-                CpArg::Local(filename) =>
-                    srcs.push(filename),
-                CpArg::Remote(rpat)    =>
-                    Err(ErrorKind::CannotCopyRemoteToRemote(rpat.clone(), dst.clone()))?
+                CpArg::Local(filename) => srcs.push(filename),
+                CpArg::Remote(rpat)    => remote_srcs.push(rpat),
+                CpArg::Stdio if raw_srcs.len() == 1
+                                       => return self.cp_stdin(dst),
+                CpArg::Stdio           => Err(ErrorKind::MultipleSourcesOneDestination)?,
             }
         }
 
+        if !remote_srcs.is_empty() {
+            if let Some(local) = srcs.first() {
+                Err(ErrorKind::MixedCopySources(local.to_path_buf(), remote_srcs[0].clone()))?;
+            }
+
+            return self.cp_remote(&remote_srcs, dst);
+        }
+
+        let mut files    = Vec::new();
+        let mut unpacked = Vec::new();
+
         if dst.is_whole_hw() {
+            let mut ignorer = self.ignorer(Path::new("."))?;
+
             for src in srcs {
+                // The shell expands wildcards before we see them, so files
+                // named here are subject to ignore rules too, but skipping
+                // one deserves a warning.
+                if !self.config.get_no_ignore() {
+                    if let Some(rule) = ignorer.check(src, src.is_dir())? {
+                        self.warn(ErrorKind::SourceIsIgnored(src.to_owned(), rule));
+                        continue;
+                    }
+                }
+
+                if let Some(format) = self.archive_to_extract(src) {
+                    let (dir, root) = self.unpack_archive(src, format)?;
+                    self.collect_dir_files(&mut self.ignorer(&root)?, &root, &mut files)?;
+                    unpacked.push(dir);
+                    continue;
+                }
+
+                if src.is_dir() {
+                    if self.config.get_recursive() {
+                        self.collect_dir_files(&mut ignorer, src, &mut files)?;
+                    } else {
+                        self.warn(ErrorKind::SourceIsDirectory(src.to_owned()));
+                    }
+                    continue;
+                }
+
                 let filename     = match self.get_base_filename(&src) {
                     Ok(s)  => s,
                     Err(e) => {
@@ -399,36 +807,212 @@ impl GscClient {
                         continue;
                     }
                 };
-                self.upload_file(src, &dst.with_pat(filename))?;
+                files.push(LocalFile { path: src.to_owned(), name: filename.to_owned() });
             }
+
+            check_unique_names(&files)?;
         } else {
-            let src = if srcs.len() == 1 {
-                &srcs[0]
-            } else {
-                Err(ErrorKind::MultipleSourcesOneDestination)?
-            };
+            if srcs.len() != 1 {
+                Err(ErrorKind::MultipleSourcesOneDestination)?;
+            }
 
-            let dsts     = self.fetch_file_list(dst)?;
+            let src = srcs[0];
+            if src.is_dir() {
+                Err(ErrorKind::DirectoryToDestinationFile(src.to_owned(), dst.clone()))?;
+            } else if self.archive_to_extract(src).is_some() {
+                Err(ErrorKind::ArchiveToDestinationFile(src.to_owned(), dst.clone()))?;
+            }
+
+            let dsts     = self.fetch_dest_file_list(dst)?;
             let filename = match dsts.len() {
                 0 => &dst.pat,
                 1 => &dsts[0].name,
                 _ => Err(dest_pat_is_multiple(dst, &dsts))?,
             };
 
-            self.upload_file(src, &dst.with_pat(filename))?;
+            files.push(LocalFile { path: src.to_owned(), name: filename.to_owned() });
+        }
+
+        self.report_ignored();
+        self.upload_files(dst.hw, &files)?;
+        v2!("Done.");
+        Ok(())
+    }
+
+    // Copies remote files to another remote location, streaming each one
+    // from the server back to the server.
+    fn cp_remote(&self, srcs: &[&RemotePattern], dst: &RemotePattern) -> Result<()> {
+        let mut files = Vec::new();
+
+        for src in srcs {
+            self.try_warn(|| {
+                for meta in self.fetch_nonempty_file_list(src)? {
+                    files.push((src.hw, meta));
+                }
+
+                Ok(())
+            });
+        }
+
+        let existing = self.fetch_all_files(dst.hw)?;
+
+        // Pairs each source file with its destination name.
+        let mut copies = Vec::new();
+
+        if dst.is_whole_hw() {
+            let mut seen: HashMap<&str, usize> = HashMap::new();
+
+            for (hw, meta) in &files {
+                if let Some(other) = seen.insert(&meta.name, *hw) {
+                    Err(ErrorKind::DuplicateUploadName(
+                        meta.name.clone(),
+                        format!("hw{}:{}", other, meta.name).into(),
+                        format!("hw{}:{}", hw, meta.name).into()))?;
+                }
+
+                copies.push((*hw, meta, meta.name.clone()));
+            }
+        } else {
+            if files.len() != 1 {
+                Err(ErrorKind::MultipleSourcesOneDestination)?;
+            }
+
+            let dsts = self.fetch_dest_file_list(dst)?;
+            let name = match dsts.len() {
+                0 => dst.pat.clone(),
+                1 => dsts[0].name.clone(),
+                _ => Err(dest_pat_is_multiple(dst, &dsts))?,
+            };
+
+            copies.push((files[0].0, &files[0].1, name));
+        }
+
+        let mut policy = self.config.get_overwrite_policy();
+        let mut chosen = Vec::new();
+
+        for (hw, meta, name) in copies {
+            let dst_file = dst.with_pat(&name);
+
+            if hw == dst.hw && meta.name == name {
+                self.warn(format!("‘{}’ is the same file as its source; skipping.", dst_file));
+            } else if !existing.iter().any(|other| other.name == name)
+                || self.is_okay_to_overwrite(&mut policy, || &dst_file)? {
+                chosen.push((hw, meta, dst_file));
+            }
+        }
+
+        if chosen.is_empty() {
+            return Ok(());
+        }
+
+        if !self.config.get_force() {
+            let sizes: Vec<_> = chosen.iter()
+                .map(|(_, meta, dst_file)| (dst_file.pat.as_str(), meta.byte_count))
+                .collect();
+            self.check_quota(dst.hw, &sizes)?;
+        }
+
+        for (hw, meta, dst_file) in &chosen {
+            let contents = self.open_raw_contents(meta)?;
+            v2!("Copying ‘hw{}:{}’ -> ‘{}’...", hw, meta.name, dst_file);
+            self.upload_body(reqwest::Body::sized(contents, meta.byte_count as u64), dst_file)?;
+        }
+
+        let remote = self.fetch_all_files(dst.hw)?;
+
+        for (hw, meta, dst_file) in &chosen {
+            match remote.iter().find(|other| other.name == dst_file.pat) {
+                Some(other) if other.byte_count == meta.byte_count => (),
+                Some(other) => self.warn(format!("Copied {} bytes from ‘hw{}:{}’, but the \
+                                                  server has {} bytes in ‘{}’.",
+                                                 meta.byte_count, hw, meta.name,
+                                                 other.byte_count, dst_file)),
+                None        => self.warn(format!("Copied ‘hw{}:{}’, but ‘{}’ is not on \
+                                                  the server.",
+                                                 hw, meta.name, dst_file)),
+            }
         }
 
         v2!("Done.");
         Ok(())
     }
 
+    // Uploads `files` and then checks that the server has each of them at
+    // the right size, returning the new remote listing.
+    fn upload_files(&self, hw: usize, files: &[LocalFile]) -> Result<Vec<messages::FileMeta>> {
+        if !self.config.get_force() {
+            let mut sizes = Vec::new();
+            for file in files {
+                sizes.push((file.name.as_str(), file.path.metadata()?.len() as usize));
+            }
+
+            self.check_quota(hw, &sizes)?;
+        }
+
+        let dst = RemotePattern { hw, pat: String::new() };
+
+        for file in files {
+            self.upload_file(&file.path, &dst.with_pat(&file.name))?;
+        }
+
+        let remote = self.fetch_all_files(hw)?;
+
+        for file in files {
+            let byte_count = file.path.metadata()?.len() as usize;
+
+            match remote.iter().find(|meta| meta.name == file.name) {
+                Some(meta) if meta.byte_count == byte_count => (),
+                Some(meta) => self.warn(format!("Uploaded {} bytes from ‘{}’, but the server \
+                                                 has {} bytes in ‘hw{}:{}’.",
+                                                byte_count, file.path.display(),
+                                                meta.byte_count, hw, meta.name)),
+                None       => self.warn(format!("Uploaded ‘{}’, but ‘hw{}:{}’ is not on \
+                                                 the server.",
+                                                file.path.display(), hw, file.name)),
+            }
+        }
+
+        Ok(remote)
+    }
+
+    // Refuses to start an upload that would run out of quota partway
+    // through. Files being replaced give their space back.
+    // `files` holds the name and size of each file to upload.
+    fn check_quota(&self, hw: usize, files: &[(&str, usize)]) -> Result<()> {
+        let submission = self.fetch_submission(hw)?;
+        let remote     = self.fetch_all_files(hw)?;
+
+        let uploading: usize = files.iter().map(|&(_, size)| size).sum();
+
+        let replacing: usize = remote.iter()
+            .filter(|meta| files.iter().any(|&(name, _)| name == meta.name))
+            .map(|meta| meta.byte_count)
+            .sum();
+
+        let needed = (submission.bytes_used + uploading).saturating_sub(replacing);
+        v3!("Quota check for hw{}: {} used, {} replaced, {} uploaded, {} allowed.",
+            hw, submission.bytes_used, replacing, uploading, submission.bytes_quota);
+
+        if needed > submission.bytes_quota {
+            Err(ErrorKind::QuotaExceeded(hw, files.len(), uploading, replacing,
+                                         submission.bytes_used, submission.bytes_quota))?
+        } else {
+            Ok(())
+        }
+    }
+
     fn upload_file(&self, src: &Path, dst: &RemotePattern) -> Result<()> {
         let src_file     = std::fs::File::open(&src)?;
+        v2!("Uploading ‘{}’ -> ‘{}’...", src.display(), dst);
+        self.upload_body(src_file, dst)
+    }
+
+    // Uploads `body` as the contents of the remote file `dst`.
+    fn upload_body<B: Into<reqwest::Body>>(&self, body: B, dst: &RemotePattern) -> Result<()> {
         let encoded_dst  = utf8_percent_encode(&dst.pat, ENCODE_SET);
         let base_uri     = self.get_uri_for_submission_files(dst.hw)?;
         let uri          = format!{"{}/{}", base_uri, encoded_dst};
-        let request      = self.http.put(&uri).body(src_file);
-        v2!("Uploading ‘{}’ -> ‘{}’...", src.display(), dst);
+        let request      = self.http.put(&uri).body(body);
         self.send_request(request)?;
 
         Ok(())
@@ -506,7 +1090,64 @@ impl GscClient {
         }
     }
 
+    pub fn deadlines(&self, ics: Option<&Path>) -> Result<()> {
+        let (me, cookie) = self.load_credentials()?;
+        let submissions  = self.fetch_submissions(&me, cookie)?;
+        let mut deadlines = Vec::new();
+
+        for short in &submissions {
+            if short.status == messages::SubmissionStatus::Closed { continue; }
+
+            let uri          = format!("{}{}", self.config.get_endpoint(), short.uri);
+            let submission   = self.fetch_submission_at(&uri, short.assignment_number)?;
+            deadlines.extend(calendar::submission_deadlines(&submission));
+        }
+
+        deadlines.sort_by_key(|deadline| (deadline.date.to_utc(), deadline.hw, deadline.kind));
+
+        if let Some(filename) = ics {
+            let file = std::fs::File::create(filename)?;
+            calendar::write_ics(io::BufWriter::new(file), &me, &deadlines)?;
+            v2!("Wrote {} deadlines to ‘{}’.", deadlines.len(), filename.display());
+        }
+
+        if self.print_listing(&deadlines)? {
+            return Ok(());
+        }
+
+        if deadlines.is_empty() {
+            v1!("No upcoming deadlines.");
+            return Ok(());
+        }
+
+        let mut table = tabular::Table::new("  {:<}  {:<}  {:<}");
+
+        for deadline in &deadlines {
+            let date = match deadline.kind {
+                calendar::DeadlineKind::Open => describe_opening(&deadline.date),
+                _                            => describe_deadline(&deadline.date),
+            };
+
+            table.add_row(tabular::Row::new()
+                .with_cell(format!("hw{}", deadline.hw))
+                .with_cell(deadline.kind.to_str())
+                .with_ansi_cell(date));
+        }
+
+        v1!("{}", table);
+
+        Ok(())
+    }
+
     pub fn deauth(&self) -> Result<()> {
+        self.check_online()?;
+
+        if self.config.get_token().is_some() {
+            self.warn("Authenticating with an API token; remove it from $GSC_TOKEN \
+                       or your dotfile to deauthenticate.");
+            return Ok(());
+        }
+
         let uri          = format!("{}/api/whoami", self.config.get_endpoint());
         let request      = self.http.delete(&uri);
         let result       = match self.send_request(request) {
@@ -539,45 +1180,58 @@ impl GscClient {
         Ok(())
     }
 
+    pub fn cache_clear(&self) -> Result<()> {
+        let dir = self.config.get_cache_dir()?;
+        cache::Cache::new(dir, 0).clear()?;
+        v2!("Cleared the cache in ‘{}’.", dir.display());
+        Ok(())
+    }
+
     pub fn cat(&self, rpats: &[RemotePattern]) -> Result<()> {
+        // Only output bound for the pager needs to be held until the end.
+        if pager::may_page(self.config.get_pager()) {
+            let mut output = Vec::new();
+            self.write_cat(&mut output, rpats)?;
+            pager::page(true, &output)
+        } else {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            self.write_cat(&mut out, rpats)?;
+            out.flush()?;
+            Ok(())
+        }
+    }
+
+    fn write_cat<W: Write>(&self, output: &mut W, rpats: &[RemotePattern]) -> Result<()> {
         for rpat in rpats {
             self.try_warn(|| {
                 let files = self.fetch_nonempty_file_list(&rpat)?;
 
                 if rpat.is_whole_hw() {
-                    let mut table   = tabular::Table::new("{:>}  {:<}");
-                    let mut line_no = 0;
-
                     for file in files {
                         if file.purpose == messages::FilePurpose::Resource { continue; }
 
-                        let uri          = format!("{}{}", self.config.get_endpoint(), file.uri);
-                        let request      = self.http.get(&uri);
-                        let response     = self.send_request(request)?;
-                        let contents     = BufReader::new(response);
+                        let contents     = BufReader::new(self.open_raw_contents(&file)?);
 
-                        table.add_heading(format!("hw{}:{}:\n", rpat.hw, file.name));
+                        // Line numbers restart with each file, under a heading
+                        // that is easy to search for in the pager.
+                        let mut table = tabular::Table::new("{:>}  {:<}");
+                        table.add_heading(format!("==> hw{}:{} <==", rpat.hw, file.name));
 
-                        for line_result in contents.lines() {
-                            line_no += 1;
+                        for (i, line_result) in contents.lines().enumerate() {
                             let line = line_result
                                 .unwrap_or_else(|e| format!("<error: {}>", e));
                             table.add_row(tabular::Row::new()
-                                .with_cell(line_no)
+                                .with_cell(i + 1)
                                 .with_cell(line.trim_end()));
                         }
 
-                        table.add_heading(String::new());
+                        writeln!(output, "{}", table)?;
                     }
 
-                    print!("{}", table);
-
                 } else {
                     for file in files {
-                        let uri          = format!("{}{}", self.config.get_endpoint(), file.uri);
-                        let request      = self.http.get(&uri);
-                        let mut response = self.send_request(request)?;
-                        response.copy_to(&mut std::io::stdout())?;
+                        io::copy(&mut self.open_raw_contents(&file)?, output)?;
                     }
                 }
 
@@ -589,6 +1243,8 @@ impl GscClient {
     }
 
     pub fn create(&mut self, username: &str) -> Result<()> {
+        self.check_online()?;
+
         let password = get_matching_passwords(username)?;
         let uri      = format!("{}/api/users", self.config.get_endpoint());
 
@@ -597,50 +1253,294 @@ impl GscClient {
             .basic_auth(username, Some(password))
             .send()?;
         let cookie_lock = CookieFile::new(self.config.get_cookie_file()?, username)?;
-        self.handle_response(&mut response, cookie_lock)?;
+        self.handle_response(&mut response, Credentials::Cookie(cookie_lock))?;
 
         v2!("Created account: {}.", username);
 
         Ok(())
     }
 
-    pub fn ls(&self, rpats: &[RemotePattern]) -> Result<()> {
+    // Returns whether any line matched.
+    pub fn grep(&self, pattern: &str, rpats: &[RemotePattern], options: &GrepOptions)
+        -> Result<bool> {
+
+        let regex  = regex::RegexBuilder::new(pattern)
+            .case_insensitive(options.ignore_case)
+            .build()?;
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let mut found = false;
+
         for rpat in rpats {
             self.try_warn(|| {
-                let files = self.fetch_nonempty_file_list(&rpat)?;
+                for file in self.fetch_nonempty_file_list(rpat)? {
+                    if file.purpose == messages::FilePurpose::Resource { continue; }
+
+                    if !is_text_media_type(&file.media_type) {
+                        v3!("Skipping ‘hw{}:{}’, which is {}.", rpat.hw, file.name, file.media_type);
+                        continue;
+                    }
+
+                    let mut contents = BufReader::new(self.open_raw_contents(&file)?);
+                    let mut buf      = Vec::new();
+                    let mut line_no  = 0;
+                    let mut count    = 0;
+
+                    while contents.read_until(b'\n', &mut buf)? > 0 {
+                        line_no += 1;
+                        let line = String::from_utf8_lossy(&buf);
+                        let line = line.trim_end_matches(['\r', '\n']);
+
+                        if regex.is_match(line) {
+                            count += 1;
+
+                            if options.files_only {
+                                break;
+                            } else if options.line_numbers && !options.count {
+                                writeln!(out, "hw{}:{}:{}: {}", rpat.hw, file.name, line_no, line)?;
+                            } else if !options.count {
+                                writeln!(out, "hw{}:{}: {}", rpat.hw, file.name, line)?;
+                            }
+                        }
+
+                        buf.clear();
+                    }
+
+                    found |= count > 0;
+
+                    if options.files_only {
+                        if count > 0 {
+                            writeln!(out, "hw{}:{}", rpat.hw, file.name)?;
+                        }
+                    } else if options.count {
+                        writeln!(out, "hw{}:{}:{}", rpat.hw, file.name, count)?;
+                    }
+                }
+
+                Ok(())
+            });
+        }
+
+        out.flush()?;
+        Ok(found)
+    }
+
+    pub fn ls(&self, rpats: &[RemotePattern], options: &LsOptions) -> Result<()> {
+        let structured  = self.config.get_output_format() != config::OutputFormat::Table;
+        let mut listing = Vec::new();
+        let mut output  = Vec::new();
+        let mut quotas  = HashMap::new();
+
+        for rpat in rpats {
+            self.try_warn(|| {
+                let mut files = self.fetch_nonempty_file_list(&rpat)?;
+                options.apply(&mut files);
+
+                if structured {
+                    listing.extend(files.into_iter()
+                        .map(|file| output::HwFile { hw: rpat.hw, file }));
+                    return Ok(());
+                }
 
                 if rpats.len() > 1 {
-                    v1!("{}:", rpat);
+                    writeln!(output, "{}:", rpat)?;
                 }
 
-                let mut table = tabular::Table::new("{:>}  {:<}  [{:<}] {:<}");
+                let mut table = if options.long {
+                    tabular::Table::new("{:>}  {:<}  [{:<}] {:<}  {:<}")
+                } else {
+                    tabular::Table::new("{:>}  {:<}  [{:<}] {:<}")
+                };
 
                 for file in &files {
-                    table.add_row(
-                        tabular::Row::new()
-                            .with_cell(file.byte_count.separate_with_commas())
-                            .with_cell(&file.upload_time)
-                            .with_cell(file.purpose.to_char())
-                            .with_cell(&file.name));
+                    let mut row = tabular::Row::new()
+                        .with_cell(file.byte_count.separate_with_commas());
+
+                    if options.long {
+                        row.add_cell(file.upload_time.format(messages::FULL_DATE_FORMAT));
+                        row.add_cell(file.purpose.to_char());
+                        row.add_cell(&file.media_type);
+                    } else {
+                        row.add_cell(&file.upload_time);
+                        row.add_cell(file.purpose.to_char());
+                    }
+
+                    table.add_row(row.with_cell(&file.name));
+                }
+
+                let total = files.iter().map(|file| file.byte_count).sum::<usize>();
+
+                writeln!(output, "{}", table)?;
+                write!(output, "{} file{}, {} bytes",
+                    files.len(),
+                    if files.len() == 1 { "" } else { "s" },
+                    total.separate_with_commas())?;
+
+                // The quota only makes sense alongside the whole homework.
+                if rpat.is_whole_hw() {
+                    let submission = match quotas.entry(rpat.hw) {
+                        hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                        hash_map::Entry::Vacant(entry)   =>
+                            entry.insert(self.fetch_submission(rpat.hw)?),
+                    };
+                    let remaining  = submission.quota_remaining();
+
+                    write!(output, "; hw{} uses {} of {} bytes ({} remaining)",
+                        rpat.hw,
+                        submission.bytes_used.separate_with_commas(),
+                        submission.bytes_quota.separate_with_commas(),
+                        style::paint(Style::QuotaRemaining(remaining),
+                                     format!("{:.1}%", remaining)))?;
                 }
 
-                v1!("{}", table);
+                writeln!(output)?;
 
                 Ok(())
             });
         }
 
-        Ok(())
+        if structured {
+            self.print_listing(&listing)?;
+        }
+
+        self.page(&output)
+    }
+
+    pub fn mv(&self, src: &RemotePattern, new_name: &str) -> Result<()> {
+        self.check_online()?;
+
+        let files = self.fetch_nonempty_file_list(src)?;
+        let file  = match files.as_slice() {
+            [file] => file,
+            _      => Err(ErrorKind::MoveNeedsOneFile(src.to_string(), files.len()))?,
+        };
+
+        let dst = src.with_pat(new_name);
+
+        if file.name == new_name {
+            v2!("‘{}’ already has that name.", dst);
+            return Ok(());
+        }
+
+        // Read the whole file first, so that nothing changes if we can’t.
+        let contents = self.fetch_file_contents(src.hw, file)?;
+        let expected = format!("{:x}", Sha256::digest(&contents));
+
+        // A file about to be replaced goes to the trash, whence rollback can
+        // recover it.
+        let remote   = self.fetch_all_files(src.hw)?;
+        let replaced = match remote.iter().find(|meta| meta.name == new_name) {
+            Some(meta) => {
+                let mut policy = self.config.get_overwrite_policy();
+                if !self.is_okay_to_overwrite(&mut policy, || &dst)? {
+                    return Ok(());
+                }
+
+                Some(self.trash_file(src.hw, meta)?)
+            }
+            None       => None,
+        };
+
+        v2!("Moving ‘hw{}:{}’ -> ‘{}’...", src.hw, file.name, dst);
+
+        let result = self.upload_body(contents, &dst).and_then(|()| {
+            let moved = self.fetch_all_files(src.hw)?.into_iter()
+                .find(|meta| meta.name == new_name);
+
+            match moved {
+                Some(meta) if self.hash_remote_file(&meta)? == expected => (),
+                _ => Err(ErrorKind::MoveNotVerified(dst.to_string()))?,
+            }
+
+            let uri = format!("{}{}", self.config.get_endpoint(), file.uri);
+            match self.send_request(self.http.delete(&uri)) {
+                Ok(_)      => Ok(()),
+                Err(error) => self.check_mv_source_deleted(src, &file.name, error),
+            }
+        });
+
+        match result {
+            Ok(()) => {
+                if replaced.is_some() {
+                    v2!("The replaced ‘{}’ is in the trash; ‘gsc restore’ can recover it.", dst);
+                }
+
+                v2!("Done.");
+                Ok(())
+            }
+
+            Err(error) => {
+                self.roll_back_mv(&dst, replaced);
+                Err(error).chain_err(|| format!("Could not move ‘hw{}:{}’; it is unchanged",
+                                                src.hw, file.name))
+            }
+        }
+    }
+
+    // Decides what a failed deletion of the source, named `name`, means. The
+    // request may have reached the server even though it reported an error,
+    // in which case the move is done and rolling it back would lose the
+    // file; only if the source is still there is `error` passed on, for
+    // rolling back.
+    fn check_mv_source_deleted(&self, src: &RemotePattern, name: &str, error: Error)
+        -> Result<()> {
+
+        let remote = match self.fetch_all_files(src.hw) {
+            Ok(remote) => remote,
+            Err(list_error) => {
+                self.warn(format!("Could not delete ‘hw{}:{}’ ({}), or check whether it \
+                                   is still there ({}); it may exist under both names.",
+                                  src.hw, name, error, list_error));
+                return Ok(());
+            }
+        };
+
+        if remote.iter().any(|meta| meta.name == name) {
+            Err(error)
+        } else {
+            self.warn(format!("Moved ‘hw{}:{}’, but the server reported an error \
+                               deleting it: {}", src.hw, name, error));
+            Ok(())
+        }
+    }
+
+    // Undoes a partial move to `dst` by putting back whatever was there
+    // before, which is in `replaced` if anything.
+    fn roll_back_mv(&self, dst: &RemotePattern, replaced: Option<trash::TrashEntry>) {
+        v2!("Rolling back...");
+
+        let result = match replaced {
+            Some(entry) => std::fs::File::open(entry.contents_path())
+                .map_err(Error::from)
+                .and_then(|file| self.upload_body(file, dst))
+                .and_then(|()| entry.remove()),
+            None        => self.fetch_all_files(dst.hw).and_then(|remote| {
+                match remote.iter().find(|meta| meta.name == dst.pat) {
+                    Some(meta) => {
+                        let uri = format!("{}{}", self.config.get_endpoint(), meta.uri);
+                        self.send_request(self.http.delete(&uri)).map(drop)
+                    }
+                    None       => Ok(()),
+                }
+            }),
+        };
+
+        if let Err(error) = result {
+            self.warn(format!("Could not roll back ‘{}’: {}", dst, error));
+        }
     }
 
     pub fn partner(&self) -> Result<()> {
         let (user, cookie) = self.load_credentials()?;
-        let uri            = self.user_uri(&user);
-        let request        = self.http.get(&uri);
-        let mut response   = self.send_request_with_cookie(request, cookie)?;
-        let user: messages::User = response.json()?;
-        self.print_partner_status(&user, "");
-        Ok(())
+        let user           = self.fetch_user(&user, cookie)?;
+
+        if self.print_listing(&user.partner_requests)? {
+            return Ok(());
+        }
+
+        let mut output = Vec::new();
+        self.write_partner_status(&mut output, &user, "")?;
+        self.page(&output)
     }
 
     pub fn partner_request(&self, hw: usize, them: &str) -> Result<()> {
@@ -678,6 +1578,8 @@ impl GscClient {
     }
 
     pub fn passwd(&self) -> Result<()> {
+        self.check_online()?;
+
         let (me, cookie) = self.load_credentials()?;
         let password     = get_matching_passwords(&me)?;
         let mut message  = messages::UserChange::default();
@@ -688,40 +1590,169 @@ impl GscClient {
         self.print_results(response)
     }
 
+    pub fn restore(&self, rspecs: &[spec::RemoteSpec]) -> Result<()> {
+        let trash     = trash::Trash::new(self.config.get_trash_dir()?);
+        let (user, _) = self.load_credentials()?;
+        let entries: Vec<_> = trash.entries()?.into_iter()
+            .filter(|entry| entry.meta.belongs_to(&user))
+            .collect();
+
+        if rspecs.is_empty() {
+            return self.list_trash(&entries);
+        }
+
+        self.check_online()?;
+
+        // For each homework, the most recently trashed file of each name.
+        let mut chosen: HashMap<usize, HashMap<String, trash::TrashEntry>> = HashMap::new();
+        let mut rest  = entries;
+
+        for rspec in rspecs {
+            let matcher = NameMatcher::new(&rspec.pat, &self.config)?;
+            let (matching, others): (Vec<_>, Vec<_>) = rest.into_iter()
+                .partition(|entry| rspec.hws.contains(entry.meta.hw)
+                           && matcher.is_match(&entry.meta.file.name));
+            rest = others;
+
+            if matching.is_empty() {
+                self.warn(ErrorKind::NothingInTrash(rspec.to_string()));
+            }
+
+            for entry in matching {
+                chosen.entry(entry.meta.hw).or_default()
+                    .insert(entry.meta.file.name.clone(), entry);
+            }
+        }
+
+        let mut hws: Vec<_> = chosen.into_iter().collect();
+        hws.sort_by_key(|&(hw, _)| hw);
+
+        for (hw, by_name) in hws {
+            self.try_warn(|| {
+                let mut entries: Vec<_> = by_name.into_values().collect();
+                entries.sort_by(|a, b| a.meta.file.name.cmp(&b.meta.file.name));
+
+                let files: Vec<_> = entries.iter()
+                    .map(|entry| LocalFile {
+                        path: entry.contents_path(),
+                        name: entry.meta.file.name.clone(),
+                    })
+                    .collect();
+
+                self.upload_files(hw, &files)?;
+
+                for entry in entries {
+                    v2!("Restored ‘hw{}:{}’.", hw, entry.meta.file.name);
+                    entry.remove()?;
+                }
+
+                Ok(())
+            });
+        }
+
+        Ok(())
+    }
+
+    fn list_trash(&self, entries: &[trash::TrashEntry]) -> Result<()> {
+        if entries.is_empty() {
+            v1!("The trash is empty.");
+            return Ok(());
+        }
+
+        let mut table = tabular::Table::new("{:<}  {:>}  {:<}");
+
+        for entry in entries {
+            table.add_row(tabular::Row::new()
+                .with_cell(&entry.meta.deleted_time)
+                .with_cell(entry.meta.file.byte_count.separate_with_commas())
+                .with_cell(format!("hw{}:{}", entry.meta.hw, entry.meta.file.name)));
+        }
+
+        v1!("{}", table);
+        Ok(())
+    }
+
     pub fn rm(&self, pats: &[RemotePattern]) -> Result<()> {
+        if !self.config.get_dry_run() {
+            self.check_online()?;
+        }
+
+        let mut doomed = Vec::new();
+
         for rpat in pats {
             self.try_warn(|| {
-                let files = self.fetch_nonempty_file_list(&rpat)?;
+                for file in self.fetch_nonempty_file_list(&rpat)? {
+                    if !doomed.iter().any(|(hw, other): &(usize, messages::FileMeta)|
+                                          *hw == rpat.hw && other.name == file.name) {
+                        doomed.push((rpat.hw, file));
+                    }
+                }
+
+                Ok(())
+            });
+        }
 
-                for file in files {
-                    let uri          = format!("{}{}", self.config.get_endpoint(), file.uri);
-                    let request      = self.http.delete(&uri);
-                    v2!("Deleting remote file ‘hw{}:{}’...", rpat.hw, file.name);
-                    self.send_request(request)?;
+        if doomed.is_empty() {
+            return Ok(());
+        }
+
+        if self.config.get_dry_run() {
+            for (hw, file) in &doomed {
+                v1!("Would delete ‘hw{}:{}’.", hw, file.name);
+            }
+
+            return Ok(());
+        }
+
+        let whole_hw = pats.iter().any(RemotePattern::is_whole_hw);
+
+        if (whole_hw || doomed.len() > 1) && !self.config.get_yes() {
+            eprintln!("This will delete:");
+            for (hw, file) in &doomed {
+                eprintln!("  hw{}:{}", hw, file.name);
+            }
+
+            if !confirm(&format!("Delete {} file{}", doomed.len(),
+                                 if doomed.len() == 1 { "" } else { "s" }))? {
+                v1!("Nothing deleted.");
+                return Ok(());
+            }
+        }
+
+        for (hw, file) in doomed {
+            self.try_warn(|| {
+                v2!("Moving ‘hw{}:{}’ to the trash...", hw, file.name);
+                let entry = self.trash_file(hw, &file)?;
+
+                v2!("Deleting remote file ‘hw{}:{}’...", hw, file.name);
+                let uri   = format!("{}{}", self.config.get_endpoint(), file.uri);
+                if let Err(error) = self.send_request(self.http.delete(&uri)) {
+                    entry.remove()?;
+                    return Err(error);
                 }
 
                 Ok(())
             });
         }
 
-        v2!("Done.");
+        v2!("Done. Use ‘gsc restore’ to undo.");
         Ok(())
     }
 
     pub fn status_hw(&self, number: usize) -> Result<()>
     {
-        let (me, cookie) = self.load_credentials()?;
-        let uri          = self.get_uri_for_submission(&me, number, cookie)?;
-        let request      = self.http.get(&uri);
-        let mut response = self.send_request(request)?;
+        let submission = self.fetch_submission(number)?;
+
+        if self.print_listing(&submission)? {
+            return Ok(());
+        }
 
-        let submission: messages::Submission = response.json()?;
         let in_evaluation   = submission.status.is_self_eval();
         let quota_remaining = submission.quota_remaining();
 
         let mut table = tabular::Table::new("  {:<}  {:<}");
         table.add_row(tabular::Row::new().with_cell("Submission status:")
-            .with_cell(submission.status));
+            .with_ansi_cell(style::paint(Style::Status(submission.status), submission.status)));
 
         if in_evaluation {
             table.add_row(tabular::Row::new().with_cell("Evaluation status:")
@@ -730,18 +1761,19 @@ impl GscClient {
 
         table
             .add_row(tabular::Row::new().with_cell("Open date:")
-                .with_cell(submission.open_date))
+                .with_cell(describe_opening(&submission.open_date)))
             .add_row(tabular::Row::new().with_cell("Submission due date:")
-                .with_cell(submission.due_date))
+                .with_ansi_cell(describe_deadline(&submission.due_date)))
             .add_row(tabular::Row::new().with_cell("Self-eval due date:")
-                .with_cell(submission.eval_date))
+                .with_ansi_cell(describe_deadline(&submission.eval_date)))
             .add_row(tabular::Row::new().with_cell("Last modified:")
                 .with_cell(submission.last_modified))
             .add_row(tabular::Row::new().with_cell("Quota remaining:")
-                .with_cell(format!("{:.1}% ({} of {} bytes used)",
-                                  quota_remaining,
-                                  submission.bytes_used.separate_with_commas(),
-                                  submission.bytes_quota.separate_with_commas())));
+                .with_ansi_cell(format!("{} ({} of {} bytes used)",
+                                        style::paint(Style::QuotaRemaining(quota_remaining),
+                                                     format!("{:.1}%", quota_remaining)),
+                                        submission.bytes_used.separate_with_commas(),
+                                        submission.bytes_quota.separate_with_commas())));
 
         let mut owners = submission.owner1.name.clone();
         if let Some(owner2) = &submission.owner2 {
@@ -749,21 +1781,23 @@ impl GscClient {
             owners += &owner2.name;
         }
 
-        v1!("hw{} ({})", number, owners);
-        v1!("{}", table);
+        let mut output = Vec::new();
+        writeln!(output, "hw{} ({})", number, owners)?;
+        writeln!(output, "{}", table)?;
 
-        Ok(())
+        self.page(&output)
     }
 
     pub fn status_user(&self) -> Result<()> {
         let (me, cookie) = self.load_credentials()?;
-        let uri          = self.user_uri(&me);
-        let request      = self.http.get(&uri);
-        let mut response = self.send_request_with_cookie(request, cookie)?;
+        let user         = self.fetch_user(&me, cookie)?;
 
-        let user: messages::User = response.json()?;
+        if self.print_listing(&user)? {
+            return Ok(());
+        }
 
-        v1!("Status for {}:\n", user.name);
+        let mut output = Vec::new();
+        writeln!(output, "Status for {}:\n", user.name)?;
 
         if user.submissions.iter().any(|s| s.status != messages::SubmissionStatus::Future) {
             let mut table = tabular::Table::new("    hw{:<}: {:>}    {:<}");
@@ -771,67 +1805,289 @@ impl GscClient {
             for s in &user.submissions {
                 let grade = match s.status {
                     messages::SubmissionStatus::Future => continue,
-                    messages::SubmissionStatus::Closed => format!("{:.1}%", 100.0 * s.grade),
+                    messages::SubmissionStatus::Closed =>
+                        style::paint(Style::Grade(s.grade), format!("{:.1}%", 100.0 * s.grade)),
                     _ => String::new(),
                 };
 
                 table.add_row(tabular::Row::new()
                     .with_cell(s.assignment_number)
-                    .with_cell(grade)
-                    .with_cell(s.status));
+                    .with_ansi_cell(grade)
+                    .with_ansi_cell(style::paint(Style::Status(s.status), s.status)));
             }
 
-            v1!("  Submissions:\n{}", table);
+            writeln!(output, "  Submissions:\n{}", table)?;
         }
 
         if !user.exam_grades.is_empty() {
-            let mut table = tabular::Table::new("    ex{:<}: {:>}%    ({:<} / {:<})");
+            let mut table = tabular::Table::new("    ex{:<}: {:>}    ({:<} / {:<})");
 
             for e in &user.exam_grades {
-                let grade = format!("{:.1}", 100.0 * e.points as f64 / e.possible as f64);
+                // An exam worth no points has no meaningful grade.
+                let grade = if e.possible == 0 {
+                    "—".to_owned()
+                } else {
+                    let ratio = e.points as f64 / e.possible as f64;
+                    style::paint(Style::Grade(ratio), format!("{:.1}%", 100.0 * ratio))
+                };
                 table.add_row(tabular::Row::new()
                     .with_cell(e.number)
-                    .with_cell(grade)
+                    .with_ansi_cell(grade)
                     .with_cell(e.points)
                     .with_cell(e.possible));
             }
 
-            v1!("  Exam grades:\n{}", table);
+            writeln!(output, "  Exam grades:\n{}", table)?;
         }
 
         if !user.partner_requests.is_empty() {
-            self.print_partner_status(&user, "  ");
-            v1!("Partner requests can be managed with the ‘gsc partner’ command.");
+            self.write_partner_status(&mut output, &user, "  ")?;
+            writeln!(output, "Partner requests can be managed with the ‘gsc partner’ command.")?;
+        }
+
+        self.page(&output)
+    }
+
+    pub fn submit(&self, hw: usize, src: &Path) -> Result<()> {
+        self.check_online()?;
+
+        let unpacked;
+        let src = match archive::ArchiveFormat::from_path(src).filter(|_| src.is_file()) {
+            Some(format) => {
+                unpacked = self.unpack_archive(src, format)?;
+                unpacked.1.as_path()
+            }
+            None         => src,
+        };
+
+        let files = self.collect_project_files(src)?;
+        self.report_ignored();
+
+        if files.is_empty() {
+            self.warn(format!("No files to submit in ‘{}’.", src.display()));
+            return Ok(());
         }
 
+        let remote = self.upload_files(hw, &files)?;
+
+        // The server decides each file’s purpose, so make sure that
+        // downloading the submission would put everything back in place.
+        for meta in remote {
+            if let Some(file) = files.iter().find(|file| file.name == meta.name) {
+                let expected: PathBuf = src.join(meta.purpose.to_dir()).join(&meta.name)
+                    .components()
+                    .collect();
+
+                if file.path != expected {
+                    self.warn(format!("Server classified ‘{}’ as a {} file, so it \
+                                       will be downloaded to ‘{}’.",
+                                      file.path.display(), meta.purpose,
+                                      expected.display()));
+                }
+            }
+        }
+
+        v2!("Done.");
         Ok(())
     }
 
+    pub fn verify(&self, hw: usize, src: &Path) -> Result<()> {
+        let files        = self.fetch_all_files(hw)?;
+        let mut problems = 0;
+
+        for meta in &files {
+            let local: PathBuf = src.join(meta.purpose.to_dir()).join(&meta.name)
+                .components()
+                .collect();
+
+            let problem = if !local.is_file() {
+                Some("is missing locally".to_owned())
+            } else if local.metadata()?.len() as usize != meta.byte_count {
+                Some(format!("differs in size ({} bytes remote, {} bytes local)",
+                             meta.byte_count, local.metadata()?.len()))
+            } else {
+                let remote_hash = self.hash_remote_file(meta)?;
+                let local_hash  = hash_local_file(&local)?;
+
+                if remote_hash == local_hash {
+                    None
+                } else {
+                    Some(format!("differs (SHA-256 {} remote, {} local)",
+                                 remote_hash, local_hash))
+                }
+            };
+
+            match problem {
+                None          => v2!("‘hw{}:{}’ matches ‘{}’.", hw, meta.name, local.display()),
+                Some(message) => {
+                    self.warn(format!("‘hw{}:{}’ {}: ‘{}’.",
+                                      hw, meta.name, message, local.display()));
+                    problems += 1;
+                }
+            }
+        }
+
+        if problems == 0 {
+            v2!("All {} files in hw{} match.", files.len(), hw);
+            Ok(())
+        } else {
+            Err(ErrorKind::VerifyFailed(hw, problems, files.len()))?
+        }
+    }
+
     pub fn whoami(&self) -> Result<()> {
-        let uri          = format!("{}/api/whoami", self.config.get_endpoint());
-        let request      = self.http.get(&uri);
-        let mut response = self.send_request(request)?;
-        let text         = response.text()?;
-        v1!("{}", text);
+        let text = self.fetch_whoami()?;
+
+        if !self.print_listing(&output::Whoami { name: text.trim().to_owned() })? {
+            v1!("{}", text);
+        }
+
         Ok(())
     }
 
     // Helper methods
 
+    // Finds the files to submit from a project directory: the regular,
+    // non-hidden, non-ignored files directly within each purpose’s directory.
+    fn collect_project_files(&self, src: &Path) -> Result<Vec<LocalFile>> {
+        let mut dirs: Vec<&str> = messages::FilePurpose::ALL.iter()
+            .map(|purpose| purpose.to_dir())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+
+        if !src.is_dir() {
+            Err(ErrorKind::BadLocalPath(src.to_owned()))?;
+        }
+
+        let mut ignorer = self.ignorer(src)?;
+        let mut result  = Vec::new();
+
+        for dir in dirs {
+            let path = src.join(dir);
+            if !path.is_dir() { continue; }
+            if dir != "." && self.skip_ignored(&mut ignorer, &path, true)? { continue; }
+
+            for entry in read_dir_sorted(&path)? {
+                if !entry.is_file() { continue; }
+
+                if let Some(file) = self.local_file(&mut ignorer, entry)? {
+                    result.push(file);
+                }
+            }
+        }
+
+        check_unique_names(&result)?;
+        Ok(result)
+    }
+
+    // The format of `src`, if it is an archive that `cp -x` should unpack.
+    fn archive_to_extract(&self, src: &Path) -> Option<archive::ArchiveFormat> {
+        if self.config.get_extract() && src.is_file() {
+            archive::ArchiveFormat::from_path(src)
+        } else {
+            None
+        }
+    }
+
+    // Unpacks an archive into a temporary directory, which is removed when
+    // the returned `TempDir` is dropped, returning also the path to treat as
+    // the archive’s root. That path ends with the archive’s name so that
+    // messages about its members say where they came from.
+    fn unpack_archive(&self, src: &Path, format: archive::ArchiveFormat)
+        -> Result<(tempfile::TempDir, PathBuf)> {
+
+        let dir  = tempfile::Builder::new().prefix("gsc-").tempdir()?;
+        let root = dir.path().join(self.get_base_filename(src)?);
+
+        v2!("Unpacking ‘{}’...", src.display());
+        std::fs::create_dir(&root)?;
+        archive::extract(src, format, &root, self.config.get_strip_components(),
+                         |message| self.warn(message))?;
+
+        Ok((dir, root))
+    }
+
+    // Finds the files to upload from `dir` and its subdirectories, skipping
+    // hidden and ignored files, and warning about links and special files.
+    fn collect_dir_files(&self, ignorer: &mut ignore::Ignorer, dir: &Path,
+                         result: &mut Vec<LocalFile>) -> Result<()> {
+
+        for entry in read_dir_sorted(dir)? {
+            let file_type = entry.symlink_metadata()?.file_type();
+
+            if file_type.is_dir() {
+                if is_hidden(&entry) { continue; }
+                if self.skip_ignored(ignorer, &entry, true)? { continue; }
+                self.collect_dir_files(ignorer, &entry, result)?;
+            } else if file_type.is_file() {
+                if let Some(file) = self.local_file(ignorer, entry)? {
+                    result.push(file);
+                }
+            } else if file_type.is_symlink() {
+                self.warn(format!("Skipping symbolic link ‘{}’.", entry.display()));
+            } else {
+                self.warn(format!("Skipping special file ‘{}’.", entry.display()));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Names a file found by walking a directory, or returns `None` if it
+    // should be skipped.
+    fn local_file(&self, ignorer: &mut ignore::Ignorer, path: PathBuf)
+        -> Result<Option<LocalFile>> {
+
+        let name = match self.get_base_filename(&path) {
+            Ok(s)  => s.to_owned(),
+            Err(e) => {
+                self.warn(e);
+                return Ok(None);
+            }
+        };
+
+        if name.starts_with('.') || self.skip_ignored(ignorer, &path, false)? {
+            Ok(None)
+        } else {
+            Ok(Some(LocalFile { path, name }))
+        }
+    }
+
+    // The files matching `rpat`, subject to the user’s matching options.
     fn fetch_file_list(&self, rpat: &RemotePattern) -> Result<Vec<messages::FileMeta>>
     {
-        let matcher      = glob(&rpat.pat)?;
-        let uri          = self.get_uri_for_submission_files(rpat.hw)?;
-        let request      = self.http.get(&uri);
-        let mut response = self.send_request(request)?;
+        let matcher = NameMatcher::new(&rpat.pat, &self.config)?;
+        let files   = self.fetch_all_files(rpat.hw)?;
 
-        let files: Vec<messages::FileMeta> = response.json()?;
+        Ok(files.into_iter()
+            .filter(|file| matcher.is_match(&file.name))
+            .collect())
+    }
+
+    // The files matching a destination pattern, which is always a plain
+    // glob: the user’s matching options only select source files.
+    fn fetch_dest_file_list(&self, rpat: &RemotePattern) -> Result<Vec<messages::FileMeta>>
+    {
+        let matcher = NameMatcher::plain(&rpat.pat)?;
+        let files   = self.fetch_all_files(rpat.hw)?;
 
         Ok(files.into_iter()
             .filter(|file| matcher.is_match(&file.name))
             .collect())
     }
 
+    // Every file in a homework, regardless of matching options.
+    fn fetch_all_files(&self, hw: usize) -> Result<Vec<messages::FileMeta>> {
+        let uri          = self.get_uri_for_submission_files(hw)?;
+
+        self.fetch_cached_listing(&uri, &format!("the files in hw{}", hw), || {
+            let request      = self.http.get(&uri);
+            let mut response = self.send_request(request)?;
+            Ok(response.json()?)
+        })
+    }
+
     fn fetch_nonempty_file_list(&self, rpat: &RemotePattern) -> Result<Vec<messages::FileMeta>> {
         let result = self.fetch_file_list(rpat)?;
 
@@ -853,21 +2109,217 @@ impl GscClient {
         }
     }
 
-    fn fetch_submissions(&self, user: &str, cookie: CookieFile)
+    // Downloads a remote file into memory, checking its size.
+    fn fetch_file_contents(&self, hw: usize, meta: &messages::FileMeta) -> Result<Vec<u8>> {
+        let contents = self.fetch_raw_contents(meta)?;
+
+        if contents.len() != meta.byte_count {
+            Err(ErrorKind::SizeMismatch(format!("hw{}:{}", hw, meta.name),
+                                        contents.len(), meta.byte_count))?;
+        }
+
+        Ok(contents)
+    }
+
+    // Opens a remote file for reading. When the cache is in use, the file is
+    // read as for `fetch_raw_contents`; otherwise it is streamed from the
+    // server without being held in memory.
+    fn open_raw_contents(&self, meta: &messages::FileMeta) -> Result<Box<dyn Read + Send>> {
+        if self.cache().is_some() {
+            return Ok(Box::new(io::Cursor::new(self.fetch_raw_contents(meta)?)));
+        }
+
+        let uri      = format!("{}{}", self.config.get_endpoint(), meta.uri);
+        let response = self.send_request(self.http.get(&uri))?;
+        Ok(Box::new(response))
+    }
+
+    // Downloads a remote file into memory, or reads it from the cache if
+    // we have this version of it. Only complete downloads are cached.
+    fn fetch_raw_contents(&self, meta: &messages::FileMeta) -> Result<Vec<u8>> {
+        let uri   = format!("{}{}", self.config.get_endpoint(), meta.uri);
+        let cache = self.cache();
+
+        if let Some(contents) = cache.as_ref().and_then(|c| c.get_contents(&uri, meta)) {
+            return Ok(contents);
+        } else if self.config.get_offline() {
+            Err(ErrorKind::NotSavedOffline(format!("‘{}’", meta.name)))?;
+        }
+
+        let request      = self.http.get(&uri);
+        let mut response = self.send_request(request)?;
+        let mut contents = Vec::with_capacity(meta.byte_count);
+        response.copy_to(&mut contents)?;
+
+        if let Some(cache) = cache {
+            if contents.len() == meta.byte_count {
+                cache.put_contents(&uri, meta, &contents);
+            }
+        }
+
+        Ok(contents)
+    }
+
+    // The listing at `uri` from the cache if it is fresh, or else as for
+    // `fetch_saved_listing`.
+    fn fetch_cached_listing<T, F>(&self, uri: &str, what: &str, fetch: F) -> Result<T>
+        where T: serde::Serialize + serde::de::DeserializeOwned,
+              F: FnOnce() -> Result<T> {
+
+        if !self.config.get_offline() && !self.fresh_listings.get() {
+            if let Some(value) = self.cache().and_then(|c| c.get_listing(uri)) {
+                return Ok(value);
+            }
+        }
+
+        self.fetch_saved_listing(uri, what, fetch)
+    }
+
+    // The listing at `uri` from `fetch`, saving it for offline use. When
+    // offline, the last one saved is used instead, whatever its age, and
+    // `what` it is gets reported along with how old it is.
+    fn fetch_saved_listing<T, F>(&self, uri: &str, what: &str, fetch: F) -> Result<T>
+        where T: serde::Serialize + serde::de::DeserializeOwned,
+              F: FnOnce() -> Result<T> {
+
+        let cache = self.cache();
+
+        if self.config.get_offline() {
+            return match cache.and_then(|c| c.get_saved_listing(uri)) {
+                Some(saved) => {
+                    ve1!("Offline: using {} from {} ago.",
+                         what, messages::describe_duration(saved.age()));
+                    Ok(saved.value)
+                }
+                None        => Err(ErrorKind::NotSavedOffline(what.to_owned()))?,
+            };
+        }
+
+        let value = fetch()?;
+
+        if let Some(cache) = cache {
+            cache.put_listing(uri, &value);
+        }
+
+        Ok(value)
+    }
+
+    // The cache is read when offline even if the user turned it off.
+    fn cache(&self) -> Option<cache::Cache> {
+        if !self.config.get_cache() && !self.config.get_offline() {
+            return None;
+        }
+
+        let dir = self.config.get_cache_dir().ok()?;
+        Some(cache::Cache::new(dir, self.config.get_cache_ttl()))
+    }
+
+    // Copies a remote file into the local trash.
+    fn trash_file(&self, hw: usize, meta: &messages::FileMeta) -> Result<trash::TrashEntry> {
+        let trash        = trash::Trash::new(self.config.get_trash_dir()?);
+        let mut contents = self.open_raw_contents(meta)?;
+        let (owner, _)   = self.load_credentials()?;
+        let meta         = trash::TrashMeta {
+            owner:          Some(owner),
+            hw,
+            deleted_time:   messages::DateTime::now(),
+            file:           meta.clone(),
+        };
+
+        trash.put(meta, &mut contents)
+    }
+
+    fn hash_remote_file(&self, meta: &messages::FileMeta) -> Result<String> {
+        let uri          = format!("{}{}", self.config.get_endpoint(), meta.uri);
+        let request      = self.http.get(&uri);
+        let mut response = self.send_request(request)?;
+        let mut hasher   = Sha256::new();
+        response.copy_to(&mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn fetch_submission(&self, hw: usize) -> Result<messages::Submission> {
+        let (me, cookie) = self.load_credentials()?;
+        let uri          = self.get_uri_for_submission(&me, hw, cookie)?;
+        self.fetch_submission_at(&uri, hw)
+    }
+
+    fn fetch_submission_at(&self, uri: &str, hw: usize) -> Result<messages::Submission> {
+        self.fetch_saved_listing(uri, &format!("the status of hw{}", hw), || {
+            let request      = self.http.get(uri);
+            let mut response = self.send_request(request)?;
+            Ok(response.json()?)
+        })
+    }
+
+    fn fetch_submissions(&self, user: &str, cookie: Credentials)
         -> Result<Vec<messages::SubmissionShort>> {
 
         let uri          = self.user_uri(user) + "/submissions";
-        let request      = self.http.get(&uri);
-        let mut response = self.send_request_with_cookie(request, cookie)?;
-        response.json()
-            .chain_err(|| "Could not understand response from server")
+
+        self.fetch_cached_listing(&uri, &format!("{}’s submissions", user), || {
+            let request      = self.http.get(&uri);
+            let mut response = self.send_request_with_cookie(request, cookie)?;
+            response.json()
+                .chain_err(|| "Could not understand response from server")
+        })
+    }
+
+    fn fetch_user(&self, user: &str, cookie: Credentials) -> Result<messages::User> {
+        let uri = self.user_uri(user);
+
+        self.fetch_saved_listing(&uri, &format!("{}’s account", user), || {
+            let request      = self.http.get(&uri);
+            let mut response = self.send_request_with_cookie(request, cookie)?;
+            Ok(response.json()?)
+        })
+    }
+
+    // Asks the server only once per run, since with a token every command
+    // needs the username, often several times.
+    fn fetch_whoami(&self) -> Result<String> {
+        if let Some(text) = self.whoami.borrow().as_ref() {
+            return Ok(text.clone());
+        }
+
+        let uri  = format!("{}/api/whoami", self.config.get_endpoint());
+        let text: String = self.fetch_saved_listing(&uri, "your username", || {
+            let request      = self.http.get(&uri);
+            let mut response = self.send_request(request)?;
+            Ok(response.text()?)
+        })?;
+
+        *self.whoami.borrow_mut() = Some(text.clone());
+        Ok(text)
     }
 
-    fn get_submission_uris(&self, user: &str, cookie: CookieFile) -> Result<Vec<Option<String>>> {
+    // The homeworks that have opened for the user, which also primes the
+    // cache of submission URIs.
+    fn fetch_open_hws(&self) -> Result<Vec<usize>> {
+        let (me, cookie) = self.load_credentials()?;
+        let submissions  = self.fetch_submissions(&me, cookie)?;
+        let uris         = self.submission_uris_from(&submissions);
+        self.submission_uris.borrow_mut().insert(me, uris);
+
+        let mut result: Vec<usize> = submissions.iter()
+            .filter(|s| s.status != messages::SubmissionStatus::Future)
+            .map(|s| s.assignment_number)
+            .collect();
+        result.sort();
+        Ok(result)
+    }
+
+    fn get_submission_uris(&self, user: &str, cookie: Credentials) -> Result<Vec<Option<String>>> {
         let submissions = self.fetch_submissions(user, cookie)?;
+        Ok(self.submission_uris_from(&submissions))
+    }
+
+    fn submission_uris_from(&self, submissions: &[messages::SubmissionShort])
+        -> Vec<Option<String>> {
+
         let mut result  = Vec::new();
 
-        for submission in &submissions {
+        for submission in submissions {
             let number = submission.assignment_number;
 
             while number >= result.len() {
@@ -877,10 +2329,10 @@ impl GscClient {
             result[number] = Some(format!("{}{}", self.config.get_endpoint(), submission.uri));
         }
 
-        Ok(result)
+        result
     }
 
-    fn get_uri_for_submission(&self, user: &str, number: usize, cookie: CookieFile)
+    fn get_uri_for_submission(&self, user: &str, number: usize, cookie: Credentials)
         -> Result<String> {
 
         let mut cache = self.submission_uris.borrow_mut();
@@ -902,7 +2354,7 @@ impl GscClient {
         self.get_uri_for_submission(&user, number, cookie).map(|uri| uri + "/files")
     }
 
-    fn handle_response(&self, response: &mut reqwest::Response, cookie_lock: CookieFile)
+    fn handle_response(&self, response: &mut reqwest::Response, cookie_lock: Credentials)
                        -> Result<()> {
 
         self.save_cookie(response, cookie_lock)?;
@@ -915,34 +2367,84 @@ impl GscClient {
         }
     }
 
+    // Applies the dotfile’s ignore patterns and the `.gscignore` files
+    // under `root`.
+    fn ignorer(&self, root: &Path) -> Result<ignore::Ignorer> {
+        let origin = self.config.get_dotfile()
+            .map_or_else(|| "dotfile".to_owned(), |path| path.display().to_string());
+        let mut global = ignore::parse_rules(
+            self.config.get_ignore_patterns().iter().map(String::as_str),
+            &origin)?;
+        global.extend(ignore::parse_rules(
+            self.config.get_ignore_args().iter().map(String::as_str),
+            "--ignore")?);
+        ignore::Ignorer::new(root, global)
+    }
+
     fn load_cookie_file(&self) -> Result<CookieFile> {
         CookieFile::lock(self.config.get_cookie_file()?)
     }
 
-    fn load_credentials(&self) -> Result<(String, CookieFile)> {
-        let cookie_file = self.load_cookie_file()?;
+    // Fails up front for commands that would change the server, rather
+    // than after asking the user questions. Such commands decide what to
+    // change from the server’s current listings, never from cached ones.
+    fn check_online(&self) -> Result<()> {
+        if self.config.get_offline() {
+            Err(ErrorKind::ChangeWhileOffline)?
+        } else {
+            self.fresh_listings.set(true);
+            Ok(())
+        }
+    }
+
+    // When an API token is configured, the cookie file isn’t touched at all.
+    fn load_auth(&self) -> Result<Credentials> {
+        match self.config.get_token() {
+            Some(token) => Ok(Credentials::Token(token.to_owned())),
+            None        => Ok(Credentials::Cookie(self.load_cookie_file()?)),
+        }
+    }
+
+    fn load_credentials(&self) -> Result<(String, Credentials)> {
+        let auth = self.load_auth()?;
 
-        let user        = match self.config.get_on_behalf() {
-            Some(s) => s,
-            None    => cookie_file.get_username()
-        }.to_owned();
+        let user = match self.config.get_on_behalf() {
+            Some(s) => s.to_owned(),
+            None    => match &auth {
+                Credentials::Cookie(cookie_file) => cookie_file.get_username().to_owned(),
+                Credentials::Token(_)            => self.fetch_whoami()?.trim().to_owned(),
+            }
+        };
 
-        Ok((user, cookie_file))
+        Ok((user, auth))
     }
 
     fn prepare_cookie(&self, mut request: reqwest::RequestBuilder,
-                      cookie_lock: &CookieFile)
+                      cookie_lock: &Credentials)
         -> Result<reqwest::RequestBuilder>
     {
-        let cookie = cookie_lock.get_cookie_header()?;
-        ve3!("> Sending cookie {}", cookie.to_str().unwrap());
-        request = request.header(reqwest::header::COOKIE, cookie);
+        match cookie_lock {
+            Credentials::Cookie(cookie_file) => {
+                let cookie = cookie_file.get_cookie_header()?;
+                ve3!("> Sending cookie {}", cookie.to_str().unwrap());
+                request = request.header(reqwest::header::COOKIE, cookie);
+            }
+
+            Credentials::Token(token) => {
+                let bearer = HeaderValue::from_str(&format!("Bearer {}", token))?;
+                ve3!("> Sending API token");
+                request = request.header(reqwest::header::AUTHORIZATION, bearer);
+            }
+        }
+
         Ok(request)
     }
 
-    fn print_partner_status(&self, user: &messages::User, indent: &str) {
+    fn write_partner_status(&self, output: &mut Vec<u8>, user: &messages::User, indent: &str)
+        -> Result<()> {
+
         if user.partner_requests.is_empty() {
-            ve1!("No outstanding partner requests.");
+            writeln!(output, "{}No outstanding partner requests.", indent)?;
         } else {
             let mut table = tabular::Table::new("    {:<} {:<}");
 
@@ -951,16 +2453,31 @@ impl GscClient {
                 let hw      = format!("hw{}:", p.assignment_number);
                 let message = match p.status {
                     Outgoing => format!("sent to {}", p.user),
-                    Incoming => format!("received from {}", p.user),
+                    Incoming => style::paint(Style::Attention,
+                                             format!("received from {}", p.user)),
                     _        => continue,
                 };
 
                 table.add_row(tabular::Row::new()
                     .with_cell(hw)
-                    .with_cell(message));
+                    .with_ansi_cell(message));
             }
 
-            v1!("{}Partner requests:\n{}", indent, table);
+            writeln!(output, "{}Partner requests:\n{}", indent, table)?;
+        }
+
+        Ok(())
+    }
+
+    // Prints `listing` if the user asked for a machine-readable format, in
+    // which case the caller should skip printing its table.
+    fn print_listing<L: output::Listing>(&self, listing: &L) -> Result<bool> {
+        match self.config.get_output_format() {
+            config::OutputFormat::Table => Ok(false),
+            format                      => {
+                output::print(format, listing)?;
+                Ok(true)
+            }
         }
     }
 
@@ -984,9 +2501,14 @@ impl GscClient {
         format!("{}/api/users/{}", self.config.get_endpoint(), user)
     }
 
-    fn save_cookie(&self, response: &reqwest::Response, mut cookie_lock: CookieFile)
+    fn save_cookie(&self, response: &reqwest::Response, cookie_lock: Credentials)
                    -> Result<()> {
 
+        let mut cookie_lock = match cookie_lock {
+            Credentials::Cookie(cookie_file) => cookie_file,
+            Credentials::Token(_)            => return Ok(()),
+        };
+
         if let Some(cookie) = response.headers().get(reqwest::header::SET_COOKIE) {
             if let Ok(cookie_text) = cookie.to_str() {
                 if let Some((key, value)) = parse_cookie(cookie_text) {
@@ -1002,22 +2524,80 @@ impl GscClient {
     fn send_request(&self, req_builder: reqwest::RequestBuilder)
         -> Result<reqwest::Response> {
 
-        let cookie = self.load_cookie_file()?;
+        let cookie = self.load_auth()?;
         self.send_request_with_cookie(req_builder, cookie)
     }
 
     fn send_request_with_cookie(&self, mut req_builder: reqwest::RequestBuilder,
-                                cookie: CookieFile)
+                                cookie: Credentials)
         -> Result<reqwest::Response> {
 
         req_builder = self.prepare_cookie(req_builder, &cookie)?;
         let request      = req_builder.build()?;
+
+        if self.config.get_offline() {
+            if request.method() == reqwest::Method::GET {
+                Err(ErrorKind::NotSavedOffline(format!("‘{}’", request.url())))?;
+            } else {
+                Err(ErrorKind::ChangeWhileOffline)?;
+            }
+        }
+
         ve3!("> Sending request to {}", request.url());
+
+        // Anything but a GET may change what the server would list.
+        if request.method() != reqwest::Method::GET {
+            if let Some(cache) = self.cache() {
+                cache.forget_listings();
+            }
+        }
+
         let mut response = self.http.execute(request)?;
         self.handle_response(&mut response, cookie)?;
         Ok(response)
     }
 
+    // Whether `path` should be left out of an upload. Skipped files are
+    // listed only if the user asked to see them.
+    fn skip_ignored(&self, ignorer: &mut ignore::Ignorer, path: &Path, is_dir: bool)
+        -> Result<bool> {
+
+        if self.config.get_no_ignore() {
+            return Ok(false);
+        }
+
+        match ignorer.check(path, is_dir)? {
+            Some(rule) => {
+                if self.config.get_show_ignored() {
+                    v1!("Ignoring ‘{}’ (matches {}).", path.display(), rule);
+                }
+                self.ignored_count.set(self.ignored_count.get() + 1);
+                Ok(true)
+            }
+            None       => Ok(false),
+        }
+    }
+
+    fn report_ignored(&self) {
+        let count = self.ignored_count.replace(0);
+
+        if count > 0 && !self.config.get_show_ignored() {
+            v2!("Skipped {} ignored file{}; pass ‘--show-ignored’ to list {}.",
+                count,
+                if count == 1 { "" } else { "s" },
+                if count == 1 { "it" } else { "them" });
+        }
+    }
+
+    // Prints `output` at verbosity level 1, through the pager if enabled.
+    fn page(&self, output: &[u8]) -> Result<()> {
+        if get_verbosity_level() >= 1 {
+            pager::page(self.config.get_pager(), output)
+        } else {
+            Ok(())
+        }
+    }
+
     fn try_warn<F, R>(&self, f: F) -> R
         where F: FnOnce() -> Result<R>,
               R: Default {
@@ -1029,7 +2609,7 @@ impl GscClient {
     }
 
     fn warn<T: std::fmt::Display>(&self, msg: T) {
-        ve1!("{}", msg);
+        ve1!("{}", style::paint_err(Style::Warning, msg));
         self.had_warning.set(true);
     }
 }
@@ -1038,6 +2618,81 @@ define_encode_set! {
     pub ENCODE_SET = [percent_encoding::PATH_SEGMENT_ENCODE_SET] | { '+' }
 }
 
+fn describe_opening(date: &messages::DateTime) -> String {
+    let from_now = date.from_now();
+    let relative = messages::describe_duration(from_now);
+
+    if from_now > chrono::Duration::zero() {
+        format!("{} (opens in {})", date, relative)
+    } else {
+        format!("{} (opened {} ago)", date, relative)
+    }
+}
+
+// Deadlines that have passed or are less than a day away are highlighted.
+fn describe_deadline(date: &messages::DateTime) -> String {
+    let from_now = date.from_now();
+    let relative = messages::describe_duration(from_now);
+
+    if from_now < chrono::Duration::zero() {
+        style::paint(Style::PastDue, format!("{} (closed {} ago)", date, relative))
+    } else if from_now < chrono::Duration::days(1) {
+        style::paint(Style::DueSoon, format!("{} (due in {})", date, relative))
+    } else {
+        format!("{} (due in {})", date, relative)
+    }
+}
+
+// The server has one flat namespace per submission, so files from
+// different directories may not share a name.
+fn check_unique_names(files: &[LocalFile]) -> Result<()> {
+    let mut seen: HashMap<&str, &Path> = HashMap::new();
+
+    for file in files {
+        if let Some(other) = seen.insert(&file.name, &file.path) {
+            Err(ErrorKind::DuplicateUploadName(file.name.clone(),
+                                               other.to_owned(),
+                                               file.path.clone()))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Whether `grep` should search files of this type.
+fn is_text_media_type(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or("").trim();
+
+    essence.starts_with("text/")
+        || essence.ends_with("+xml")
+        || essence.ends_with("+json")
+        || matches!(essence, "application/json" | "application/xml" | "application/javascript"
+                             | "application/x-sh" | "application/x-yaml" | "application/yaml"
+                             | "application/toml")
+}
+
+fn hash_local_file(path: &Path) -> Result<String> {
+    let mut file   = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+fn read_dir_sorted(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(dir)
+        .chain_err(|| format!("Could not read directory: {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
 fn get_matching_passwords(username: &str) -> Result<String> {
     let password1 = prompt_password("New password", username)?;
     let password2 = prompt_password("Confirm password", username)?;
@@ -1049,9 +2704,63 @@ fn get_matching_passwords(username: &str) -> Result<String> {
     }
 }
 
-fn glob(pattern: &str) -> Result<globset::GlobMatcher> {
-    let real_pattern = if pattern.is_empty() { "*" } else { pattern };
-    Ok(globset::Glob::new(real_pattern)?.compile_matcher())
+// Selects remote files by name. Glob patterns may use braces, as in
+// `{*.h,*.c}`; regexes must match the whole name.
+struct NameMatcher {
+    include:    NamePattern,
+    exclude:    globset::GlobSet,
+}
+
+enum NamePattern {
+    Glob(globset::GlobMatcher),
+    Regex(regex::Regex),
+}
+
+impl NameMatcher {
+    fn new(pattern: &str, config: &config::Config) -> Result<Self> {
+        let ignore_case = config.get_ignore_case();
+
+        let include = if config.get_regex() && !pattern.is_empty() {
+            NamePattern::Regex(regex::RegexBuilder::new(&format!("^(?:{})$", pattern))
+                .case_insensitive(ignore_case)
+                .build()?)
+        } else {
+            let real_pattern = if pattern.is_empty() { "*" } else { pattern };
+            NamePattern::Glob(glob(real_pattern, ignore_case)?.compile_matcher())
+        };
+
+        let mut exclude = globset::GlobSetBuilder::new();
+        for pattern in config.get_exclude_patterns() {
+            exclude.add(glob(pattern, ignore_case)?);
+        }
+
+        Ok(NameMatcher { include, exclude: exclude.build()? })
+    }
+
+    // Matches `pattern` as a glob, ignoring the user’s matching options.
+    fn plain(pattern: &str) -> Result<Self> {
+        let real_pattern = if pattern.is_empty() { "*" } else { pattern };
+
+        Ok(NameMatcher {
+            include: NamePattern::Glob(glob(real_pattern, false)?.compile_matcher()),
+            exclude: globset::GlobSet::empty(),
+        })
+    }
+
+    fn is_match(&self, name: &str) -> bool {
+        let included = match &self.include {
+            NamePattern::Glob(glob)   => glob.is_match(name),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        };
+
+        included && !self.exclude.is_match(name)
+    }
+}
+
+fn glob(pattern: &str, ignore_case: bool) -> Result<globset::Glob> {
+    Ok(globset::GlobBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()?)
 }
 
 pub fn parse_cookie(cookie: &str) -> Option<(String, String)> {
@@ -1067,6 +2776,24 @@ pub fn parse_cookie(cookie: &str) -> Option<(String, String)> {
     })
 }
 
+// Asks a yes-or-no question on the terminal, defaulting to no. Fails if
+// there is no one to answer it.
+fn confirm(question: &str) -> Result<bool> {
+    if !atty::is(atty::Stream::Stdin) {
+        Err(ErrorKind::CannotConfirm)?;
+    }
+
+    eprint!("{} [y/N]? ", question);
+    io::stderr().flush()?;
+
+    let mut buf = String::new();
+    if io::stdin().lock().read_line(&mut buf)? == 0 {
+        Err(ErrorKind::CannotConfirm)?;
+    }
+
+    Ok(buf.chars().flat_map(char::to_lowercase).next() == Some('y'))
+}
+
 fn prompt_password(prompt: &str, username: &str) -> Result<String> {
     let prompt   = format!("{} for {}: ", prompt, username);
     let password = rpassword::prompt_password_stderr(&prompt)?;
@@ -1093,11 +2820,30 @@ impl RemotePattern {
     }
 }
 
+impl LsOptions {
+    fn apply(&self, files: &mut Vec<messages::FileMeta>) {
+        if !self.purposes.is_empty() {
+            files.retain(|file| self.purposes.contains(&file.purpose));
+        }
+
+        match self.sort {
+            LsSort::Server => (),
+            LsSort::Time   => files.sort_by_key(|file| std::cmp::Reverse(file.upload_time.to_utc())),
+            LsSort::Size   => files.sort_by_key(|file| std::cmp::Reverse(file.byte_count)),
+        }
+
+        if self.reverse {
+            files.reverse();
+        }
+    }
+}
+
 impl CpArg {
     pub fn is_whole_hw(&self) -> bool {
         match self {
             CpArg::Local(_)     => false,
             CpArg::Remote(rpat) => rpat.is_whole_hw(),
+            CpArg::Stdio        => false,
         }
     }
 }
@@ -0,0 +1,214 @@
+//! Submission manifests, which say what files a homework should contain.
+//!
+//! A manifest is a YAML map from file purposes to rules, for example:
+//!
+//! ```yaml
+//! source:
+//!   required: ["*.c", "*.h"]
+//!   forbidden: ["*.o"]
+//!   max_size: 65536
+//! test:
+//!   required: ["test_*.c"]
+//! config:
+//!   required: ["Makefile"]
+//!   optional: ["*.cmake"]
+//! ```
+//!
+//! Every remote file must match a `required` or `optional` pattern of its
+//! own purpose; anything else is reported as unexpected.
+
+use super::errors::*;
+use super::messages::{FileMeta, FilePurpose};
+
+use serde_derive::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of a manifest file in a project directory.
+pub const MANIFEST_NAME: &str = ".gscmanifest";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Manifest(pub BTreeMap<FilePurpose, PurposeRules>);
+
+/// The rules for files of one purpose.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PurposeRules {
+    /// Each pattern must match at least one file.
+    #[serde(default)]
+    pub required:   Vec<String>,
+    /// Files matching these are allowed but not needed.
+    #[serde(default)]
+    pub optional:   Vec<String>,
+    /// Files matching these must not be submitted.
+    #[serde(default)]
+    pub forbidden:  Vec<String>,
+    /// The largest allowed size of each file, in bytes.
+    #[serde(default)]
+    pub max_size:   Option<usize>,
+}
+
+/// A way that a submission fails to agree with its manifest.
+#[derive(Debug)]
+pub enum Problem {
+    Missing { purpose: FilePurpose, pattern: String },
+    Forbidden { file: String, pattern: String },
+    TooLarge { file: String, size: usize, limit: usize },
+    WrongPurpose { file: String, expected: FilePurpose, actual: FilePurpose },
+    Unexpected { file: String, purpose: FilePurpose },
+}
+
+struct CompiledRules<'a> {
+    purpose:    FilePurpose,
+    required:   Vec<(&'a str, globset::GlobMatcher)>,
+    allowed:    globset::GlobSet,
+    forbidden:  Vec<(&'a str, globset::GlobMatcher)>,
+    max_size:   Option<usize>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .chain_err(|| format!("Could not read manifest: {}", path.display()))?;
+        let parsed = serde_yaml::from_str(&contents)
+            .chain_err(|| format!("Could not parse manifest: {}", path.display()))?;
+        Ok(parsed)
+    }
+
+    /// Finds the manifest for homework `hw`: the nearest `.gscmanifest-hw<N>`
+    /// in the current directory or its ancestors, or else the nearest
+    /// `.gscmanifest`, or else `hw<N>.yaml` in the course’s manifest
+    /// directory, if one is configured.
+    pub fn find(hw: usize, course_dir: Option<&Path>) -> Result<Option<PathBuf>> {
+        let cwd = std::env::current_dir()?;
+
+        for name in &[format!("{}-hw{}", MANIFEST_NAME, hw), MANIFEST_NAME.to_owned()] {
+            for dir in cwd.ancestors() {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    return Ok(Some(candidate));
+                }
+            }
+        }
+
+        if let Some(dir) = course_dir {
+            let candidate = dir.join(format!("hw{}.yaml", hw));
+            if candidate.is_file() {
+                return Ok(Some(candidate));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Compares the remote files with the manifest.
+    pub fn check(&self, files: &[FileMeta]) -> Result<Vec<Problem>> {
+        let rules = self.0.iter()
+            .map(|(purpose, rules)| rules.compile(*purpose))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut problems = Vec::new();
+
+        for file in files {
+            let own = rules.iter().find(|r| r.purpose == file.purpose);
+
+            if let Some(own) = own {
+                if let Some((pattern, _)) = own.forbidden.iter()
+                    .find(|(_, m)| m.is_match(&file.name)) {
+
+                    problems.push(Problem::Forbidden {
+                        file:    file.name.clone(),
+                        pattern: pattern.to_string(),
+                    });
+                    continue;
+                }
+
+                if let Some(limit) = own.max_size {
+                    if file.byte_count > limit {
+                        problems.push(Problem::TooLarge {
+                            file:  file.name.clone(),
+                            size:  file.byte_count,
+                            limit,
+                        });
+                    }
+                }
+
+                if own.allowed.is_match(&file.name) { continue; }
+            }
+
+            match rules.iter().find(|r| r.allowed.is_match(&file.name)) {
+                Some(other) => problems.push(Problem::WrongPurpose {
+                    file:     file.name.clone(),
+                    expected: other.purpose,
+                    actual:   file.purpose,
+                }),
+                None        => problems.push(Problem::Unexpected {
+                    file:     file.name.clone(),
+                    purpose:  file.purpose,
+                }),
+            }
+        }
+
+        for rule in &rules {
+            for (pattern, matcher) in &rule.required {
+                let present = files.iter()
+                    .any(|f| f.purpose == rule.purpose && matcher.is_match(&f.name));
+
+                if !present {
+                    problems.push(Problem::Missing {
+                        purpose: rule.purpose,
+                        pattern: pattern.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(problems)
+    }
+}
+
+impl PurposeRules {
+    fn compile(&self, purpose: FilePurpose) -> Result<CompiledRules<'_>> {
+        let mut allowed = globset::GlobSetBuilder::new();
+        for pattern in self.required.iter().chain(&self.optional) {
+            allowed.add(globset::Glob::new(pattern)?);
+        }
+
+        Ok(CompiledRules {
+            purpose,
+            required:  matchers(&self.required)?,
+            allowed:   allowed.build()?,
+            forbidden: matchers(&self.forbidden)?,
+            max_size:  self.max_size,
+        })
+    }
+}
+
+fn matchers(patterns: &[String]) -> Result<Vec<(&str, globset::GlobMatcher)>> {
+    patterns.iter()
+        .map(|p| Ok((p.as_str(), globset::Glob::new(p)?.compile_matcher())))
+        .collect()
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Problem::*;
+
+        match self {
+            Missing { purpose, pattern } =>
+                write!(f, "Missing required {} file matching ‘{}’.", purpose, pattern),
+            Forbidden { file, pattern } =>
+                write!(f, "File ‘{}’ is forbidden (matches ‘{}’).", file, pattern),
+            TooLarge { file, size, limit } =>
+                write!(f, "File ‘{}’ is {} bytes, but the limit is {}.", file, size, limit),
+            WrongPurpose { file, expected, actual } =>
+                write!(f, "File ‘{}’ should be a {} file, but it is a {} file.",
+                       file, expected, actual),
+            Unexpected { file, purpose } =>
+                write!(f, "Unexpected {} file ‘{}’.", purpose, file),
+        }
+    }
+}
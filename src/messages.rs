@@ -1,6 +1,32 @@
+use chrono::{Local, Utc};
+use lazy_static::lazy_static;
 use serde_derive::{Serialize, Deserialize};
 
-#[derive(Clone, Deserialize, Debug)]
+use super::errors::{Error, syntax_error};
+
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The default `strftime`-style format for displaying dates.
+pub const DEFAULT_DATE_FORMAT: &str = "%a %b %d %Y %H:%M %Z";
+
+/// The format for dates in long listings, which show every field.
+pub const FULL_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+lazy_static! {
+    static ref DATE_FORMAT: RwLock<String> = RwLock::new(DEFAULT_DATE_FORMAT.to_owned());
+}
+
+static DATE_IN_UTC: AtomicBool = AtomicBool::new(false);
+
+/// Sets how dates are displayed: in UTC or the local timezone, and with
+/// which `strftime`-style format.
+pub fn set_date_display(utc: bool, format: &str) {
+    DATE_IN_UTC.store(utc, Ordering::Relaxed);
+    *DATE_FORMAT.write().unwrap() = format.to_owned();
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct DateTime(chrono::DateTime<chrono::offset::FixedOffset>);
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -70,7 +96,7 @@ pub struct GraderEval {
     pub status:         GraderEvalStatus,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct FileMeta {
     pub byte_count:         usize,
     pub media_type:         String,
@@ -112,13 +138,13 @@ pub enum UserRole {
     Admin,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct UserShort {
     pub name:               String,
     pub uri:                String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct User {
     pub name:               String,
     pub uri:                String,
@@ -155,7 +181,7 @@ pub enum SubmissionEvalStatus {
     Complete,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct SubmissionShort {
     pub assignment_number:  usize,
     pub id:                 usize,
@@ -167,7 +193,7 @@ pub struct SubmissionShort {
     pub owner2:             Option<UserShort>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Submission {
     pub assignment_number:  usize,
     pub id:                 usize,
@@ -212,9 +238,64 @@ pub struct SubmissionChange {
     pub owner2:             Option<()>,
 }
 
+impl DateTime {
+    pub fn now() -> Self {
+        let now = Local::now();
+        DateTime(now.with_timezone(now.offset()))
+    }
+
+    pub fn to_rfc3339(&self) -> String {
+        self.0.to_rfc3339()
+    }
+
+    pub fn to_utc(&self) -> chrono::DateTime<Utc> {
+        self.0.with_timezone(&Utc)
+    }
+
+    /// How far in the future this date is; negative if it has passed.
+    pub fn from_now(&self) -> chrono::Duration {
+        self.0.signed_duration_since(Utc::now())
+    }
+
+    /// Formats the date in the display timezone, but with the given
+    /// `strftime`-style format.
+    pub fn format(&self, format: &str) -> String {
+        if DATE_IN_UTC.load(Ordering::Relaxed) {
+            self.0.with_timezone(&Utc).format(format).to_string()
+        } else {
+            self.0.with_timezone(&Local).format(format).to_string()
+        }
+    }
+}
+
 impl std::fmt::Display for DateTime {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.0.format("%b %d %H:%M"))
+        f.write_str(&self.format(&DATE_FORMAT.read().unwrap()))
+    }
+}
+
+/// Describes the magnitude of a duration using its two largest units,
+/// e.g. “1 day 3 h” or “25 min”.
+pub fn describe_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes().abs();
+    let days    = minutes / (24 * 60);
+    let hours   = minutes / 60 % 24;
+    let minutes = minutes % 60;
+
+    let day_s   = if days == 1 { "day" } else { "days" };
+
+    if days > 0 && hours > 0 {
+        format!("{} {} {} h", days, day_s, hours)
+    } else if days > 0 {
+        format!("{} {}", days, day_s)
+    } else if hours > 0 && minutes > 0 {
+        format!("{} h {} min", hours, minutes)
+    } else if hours > 0 {
+        format!("{} h", hours)
+    } else if minutes > 0 {
+        format!("{} min", minutes)
+    } else {
+        "less than a minute".to_owned()
     }
 }
 
@@ -282,6 +363,14 @@ impl Default for SubmissionChange {
 }
 
 impl FilePurpose {
+    pub const ALL: [FilePurpose; 5] = [
+        FilePurpose::Source,
+        FilePurpose::Test,
+        FilePurpose::Config,
+        FilePurpose::Resource,
+        FilePurpose::Log,
+    ];
+
     pub fn to_char(&self) -> char {
         use self::FilePurpose::*;
 
@@ -307,6 +396,32 @@ impl FilePurpose {
     }
 }
 
+impl std::str::FromStr for FilePurpose {
+    type Err = Error;
+
+    /// Parses a purpose from its name or its letter.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        FilePurpose::ALL.iter()
+            .find(|purpose| purpose.to_string() == s || purpose.to_char().to_string() == s)
+            .cloned()
+            .ok_or_else(|| syntax_error("file purpose", s).into())
+    }
+}
+
+impl std::fmt::Display for FilePurpose {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use self::FilePurpose::*;
+
+        f.write_str(match self {
+            Source   => "source",
+            Test     => "test",
+            Config   => "config",
+            Resource => "resource",
+            Log      => "log",
+        })
+    }
+}
+
 impl Default for UserChange {
     fn default() -> Self {
         UserChange {
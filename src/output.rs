@@ -0,0 +1,275 @@
+//! Machine-readable output for the listing commands.
+//!
+//! JSON and YAML serialize the listing itself; CSV flattens it into one
+//! row per record. Field and column names are part of the interface, so
+//! scripts may depend on them: change them only by adding new ones.
+
+use super::calendar::Deadline;
+use super::gradebook::Report;
+use super::config::OutputFormat;
+use super::errors::*;
+use super::messages::*;
+
+use serde::Serialize;
+use serde_derive::Serialize;
+
+use std::io::{self, Write};
+
+/// A listing that can be printed in one of the machine-readable formats.
+pub trait Listing: Serialize {
+    /// The CSV column names.
+    fn csv_header(&self) -> Vec<String>;
+
+    /// The CSV rows, each with one field per column.
+    fn csv_rows(&self) -> Vec<Vec<String>>;
+}
+
+/// A remote file along with the homework it belongs to.
+#[derive(Serialize, Debug)]
+pub struct HwFile {
+    pub hw:     usize,
+    #[serde(flatten)]
+    pub file:   FileMeta,
+}
+
+/// The owners of a submission.
+#[derive(Serialize, Debug)]
+pub struct Owners {
+    pub owner1: String,
+    pub owner2: Option<String>,
+}
+
+/// The authenticated user.
+#[derive(Serialize, Debug)]
+pub struct Whoami {
+    pub name:   String,
+}
+
+/// Prints `listing` to stdout in the given format. Tables are the caller’s
+/// responsibility, since each command lays out its own.
+pub fn print<L: Listing>(format: OutputFormat, listing: &L) -> Result<()> {
+    let stdout  = io::stdout();
+    let mut out = stdout.lock();
+
+    match format {
+        OutputFormat::Table => unreachable!("tables are printed by the caller"),
+
+        OutputFormat::Json  => {
+            serde_json::to_writer_pretty(&mut out, listing)?;
+            writeln!(out)?;
+        }
+
+        OutputFormat::Yaml  => {
+            serde_yaml::to_writer(&mut out, listing)?;
+            writeln!(out)?;
+        }
+
+        OutputFormat::Csv   => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(listing.csv_header())?;
+            for row in listing.csv_rows() {
+                writer.write_record(&row)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+// The serialized name of an enum variant, e.g. `self_eval`, so that CSV
+// agrees with JSON and YAML.
+fn variant<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        _                                => String::new(),
+    }
+}
+
+fn header(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn opt_name(user: &Option<UserShort>) -> String {
+    user.as_ref().map(|u| u.name.clone()).unwrap_or_default()
+}
+
+impl Listing for Vec<HwFile> {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["hw", "name", "purpose", "byte_count", "media_type", "upload_time", "uri"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.iter()
+            .map(|HwFile { hw, file }| vec![
+                hw.to_string(),
+                file.name.clone(),
+                variant(&file.purpose),
+                file.byte_count.to_string(),
+                file.media_type.clone(),
+                file.upload_time.to_rfc3339(),
+                file.uri.clone(),
+            ])
+            .collect()
+    }
+}
+
+impl Listing for Vec<Deadline> {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["hw", "kind", "date", "extended"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.iter()
+            .map(|d| vec![
+                d.hw.to_string(),
+                variant(&d.kind),
+                d.date.to_rfc3339(),
+                d.extended.to_string(),
+            ])
+            .collect()
+    }
+}
+
+impl Listing for User {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["kind", "number", "status", "grade", "points", "possible"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let submissions = self.submissions.iter()
+            .map(|s| vec![
+                "hw".to_owned(),
+                s.assignment_number.to_string(),
+                variant(&s.status),
+                s.grade.to_string(),
+                String::new(),
+                String::new(),
+            ]);
+
+        let exams = self.exam_grades.iter()
+            .map(|e| vec![
+                "exam".to_owned(),
+                e.number.to_string(),
+                String::new(),
+                if e.possible == 0 {
+                    String::new()
+                } else {
+                    (e.points as f64 / e.possible as f64).to_string()
+                },
+                e.points.to_string(),
+                e.possible.to_string(),
+            ]);
+
+        submissions.chain(exams).collect()
+    }
+}
+
+impl Listing for Vec<PartnerRequest> {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["hw", "user", "status"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.iter()
+            .map(|p| vec![
+                p.assignment_number.to_string(),
+                p.user.clone(),
+                variant(&p.status),
+            ])
+            .collect()
+    }
+}
+
+impl Listing for Submission {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["hw", "id", "status", "eval_status", "grade", "owner1", "owner2",
+                 "open_date", "due_date", "eval_date", "last_modified",
+                 "bytes_used", "bytes_quota"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.assignment_number.to_string(),
+            self.id.to_string(),
+            variant(&self.status),
+            variant(&self.eval_status),
+            self.grade.to_string(),
+            self.owner1.name.clone(),
+            opt_name(&self.owner2),
+            self.open_date.to_rfc3339(),
+            self.due_date.to_rfc3339(),
+            self.eval_date.to_rfc3339(),
+            self.last_modified.to_rfc3339(),
+            self.bytes_used.to_string(),
+            self.bytes_quota.to_string(),
+        ]]
+    }
+}
+
+impl Listing for Vec<SubmissionShort> {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["hw", "id", "status", "grade", "owner1", "owner2"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.iter()
+            .map(|s| vec![
+                s.assignment_number.to_string(),
+                s.id.to_string(),
+                variant(&s.status),
+                s.grade.to_string(),
+                s.owner1.name.clone(),
+                opt_name(&s.owner2),
+            ])
+            .collect()
+    }
+}
+
+impl Listing for Report {
+    fn csv_header(&self) -> Vec<String> {
+        let mut result = header(&["username", "section"]);
+        result.extend(self.homeworks.iter().map(|hw| format!("hw{}", hw)));
+        result.extend(self.exams.iter().map(|exam| format!("exam{}", exam)));
+        result.extend(header(&["homework_total", "exam_total", "percent", "letter"]));
+        result
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let opt = |grade: &Option<f64>| grade.map(|g| g.to_string()).unwrap_or_default();
+
+        self.students.iter()
+            .map(|s| {
+                let mut row = vec![s.username.clone(), s.section.clone().unwrap_or_default()];
+                row.extend(s.homework.iter().chain(&s.exams).map(opt));
+                row.extend(vec![
+                    opt(&s.homework_total),
+                    opt(&s.exam_total),
+                    s.percent.to_string(),
+                    s.letter.clone(),
+                ]);
+                row
+            })
+            .collect()
+    }
+}
+
+impl Listing for Owners {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["owner1", "owner2"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.owner1.clone(), self.owner2.clone().unwrap_or_default()]]
+    }
+}
+
+impl Listing for Whoami {
+    fn csv_header(&self) -> Vec<String> {
+        header(&["name"])
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.name.clone()]]
+    }
+}
@@ -0,0 +1,72 @@
+//! Paging long output through `$PAGER`.
+
+use super::errors::*;
+
+use vlog::*;
+
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Whether `page` might run the pager, in which case output should be
+/// gathered up for it; otherwise it can go straight to stdout.
+pub fn may_page(enabled: bool) -> bool {
+    enabled && atty::is(atty::Stream::Stdout)
+}
+
+/// Writes `output` to stdout, through the pager if `enabled`, stdout is a
+/// terminal, and the output is too tall to fit on the screen.
+pub fn page(enabled: bool, output: &[u8]) -> Result<()> {
+    if may_page(enabled) {
+        if let Some((width, height)) = term_size::dimensions_stdout() {
+            if screen_lines(output, width) >= height {
+                match run_pager(output) {
+                    Ok(())     => return Ok(()),
+                    Err(error) => ve3!("Could not run pager: {}", error),
+                }
+            }
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    out.write_all(output)?;
+    out.flush()?;
+    Ok(())
+}
+
+// How many lines `output` takes up on a screen `width` columns wide.
+fn screen_lines(output: &[u8], width: usize) -> usize {
+    let width = width.max(1);
+
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(|line| 1 + line.chars().count().saturating_sub(1) / width)
+        .sum()
+}
+
+fn run_pager(output: &[u8]) -> io::Result<()> {
+    let pager = env::var("PAGER").ok()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_owned());
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&pager)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    // The user may quit the pager before reading everything, which closes
+    // the pipe; that isn’t an error.
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(output) {
+            Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+            result => result?,
+        }
+    }
+
+    child.wait()?;
+    Ok(())
+}
@@ -0,0 +1,202 @@
+//! The syntax of command-line arguments that name homeworks and files.
+//!
+//! A remote spec is `hw<SET>` or `hw<SET>:<PATTERN>`, where the set is a
+//! single number (`hw3`), a range (`hw1-4`), a list of numbers and ranges
+//! (`hw1,3,5-6`), or `*` for every homework. Sets other than a single
+//! number are expanded against the user’s submissions by
+//! [`GscClient::expand_specs`](super::GscClient::expand_specs).
+
+use super::errors::*;
+use super::{CpArg, RemotePattern};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use std::fmt;
+use std::path::PathBuf;
+
+mod re {
+    use super::*;
+
+    lazy_static! {
+        pub static ref HW_ONLY:         Regex = Regex::new(r"^hw(\d+):?$").unwrap();
+        pub static ref HW_OPT_FILE:     Regex = Regex::new(
+            r"^hw(\*|\d+(?:-\d+)?(?:,\d+(?:-\d+)?)*)(?::(.*))?$").unwrap();
+        pub static ref HW_FILE:         Regex = Regex::new(
+            r"^hw(\*|\d+(?:-\d+)?(?:,\d+(?:-\d+)?)*):(.*)$").unwrap();
+        pub static ref LOCAL_FILE:      Regex = Regex::new(r"^:(.+)$").unwrap();
+    }
+}
+
+/// A set of homework numbers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HwSet {
+    /// Every homework.
+    All,
+    /// The union of these inclusive ranges.
+    Ranges(Vec<(usize, usize)>),
+}
+
+/// A remote pattern that may name several homeworks.
+#[derive(Clone, Debug)]
+pub struct RemoteSpec {
+    pub hws:    HwSet,
+    pub pat:    String,
+}
+
+/// A `cp` argument before its homeworks are expanded.
+pub enum CpSpec {
+    Local(PathBuf),
+    Remote(RemoteSpec),
+    /// `-`, for stdin or stdout.
+    Stdio,
+}
+
+pub fn parse_hw(spec: &str) -> Result<usize> {
+    if let Some(i) = re::HW_ONLY.captures(spec)
+        .and_then(|captures| captures.get(1))
+        .and_then(|s| s.as_str().parse().ok()) {
+        Ok(i)
+    } else {
+        Err(syntax_error("homework spec", spec))?
+    }
+}
+
+pub fn parse_hw_opt_file(spec: &str) -> Result<RemoteSpec> {
+    let captures  = re::HW_OPT_FILE.captures(spec)
+        .ok_or_else(|| syntax_error("homework or file spec", spec))?;
+    let capture1  = captures.get(1).unwrap().as_str();
+    let capture2  = captures.get(2).map(|c| c.as_str());
+    let hws       = parse_hw_set(capture1)?;
+    let pat       = capture2.unwrap_or("").to_owned();
+    Ok(RemoteSpec{hws, pat})
+}
+
+pub fn parse_hw_file(file_spec: &str) -> Result<RemoteSpec> {
+    let captures  = re::HW_FILE.captures(file_spec)
+        .ok_or_else(|| syntax_error("remote file or homework spec", file_spec))?;
+    let capture1  = captures.get(1).unwrap().as_str();
+    let capture2  = captures.get(2).unwrap().as_str();
+    let hws       = parse_hw_set(capture1)?;
+    let pat       = capture2.to_owned();
+    Ok(RemoteSpec{hws, pat})
+}
+
+pub fn parse_cp_arg(spec: &str) -> Result<CpSpec> {
+    if spec.is_empty() {
+        Err(syntax_error("file name", spec))?
+    } else if spec == "-" {
+        Ok(CpSpec::Stdio)
+    } else if let Some(captures) = re::LOCAL_FILE.captures(spec) {
+        let filename = captures.get(1).unwrap().as_str().to_owned();
+        Ok(CpSpec::Local(filename.into()))
+    } else if spec.contains(':') {
+        let rp = parse_hw_file(spec)?;
+        Ok(CpSpec::Remote(rp))
+    } else {
+        Ok(CpSpec::Local(spec.into()))
+    }
+}
+
+// Parses what follows `hw`, which the regexes have already checked.
+fn parse_hw_set(set: &str) -> Result<HwSet> {
+    if set == "*" {
+        return Ok(HwSet::All);
+    }
+
+    let mut ranges = Vec::new();
+
+    for item in set.split(',') {
+        let (lo, hi) = match item.find('-') {
+            Some(i) => (item[.. i].parse()?, item[i + 1 ..].parse()?),
+            None    => {
+                let n = item.parse()?;
+                (n, n)
+            }
+        };
+
+        if lo > hi {
+            Err(syntax_error("homework range", item))?;
+        }
+
+        ranges.push((lo, hi));
+    }
+
+    Ok(HwSet::Ranges(ranges))
+}
+
+impl HwSet {
+    pub fn contains(&self, hw: usize) -> bool {
+        match self {
+            HwSet::All            => true,
+            HwSet::Ranges(ranges) => ranges.iter().any(|&(lo, hi)| lo <= hw && hw <= hi),
+        }
+    }
+
+    /// The homework number, if the set names exactly one.
+    pub fn single(&self) -> Option<usize> {
+        match self {
+            HwSet::Ranges(ranges) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 =>
+                Some(ranges[0].0),
+            _ => None,
+        }
+    }
+}
+
+impl RemoteSpec {
+    pub fn is_whole_hw(&self) -> bool {
+        self.pat.is_empty()
+    }
+
+    /// The pattern for homework `hw`, which should be in this spec’s set.
+    pub fn for_hw(&self, hw: usize) -> RemotePattern {
+        RemotePattern { hw, pat: self.pat.clone() }
+    }
+}
+
+impl CpSpec {
+    pub fn is_whole_hw(&self) -> bool {
+        match self {
+            CpSpec::Local(_)     => false,
+            CpSpec::Remote(spec) => spec.is_whole_hw(),
+            CpSpec::Stdio        => false,
+        }
+    }
+
+    /// Converts to a [`CpArg`] without expanding, if that names at most one
+    /// homework.
+    pub fn to_single(&self) -> Option<CpArg> {
+        match self {
+            CpSpec::Local(path)  => Some(CpArg::Local(path.clone())),
+            CpSpec::Remote(spec) => spec.hws.single().map(|hw| CpArg::Remote(spec.for_hw(hw))),
+            CpSpec::Stdio        => Some(CpArg::Stdio),
+        }
+    }
+}
+
+impl fmt::Display for HwSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HwSet::All            => f.write_str("*"),
+            HwSet::Ranges(ranges) => {
+                for (i, &(lo, hi)) in ranges.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+
+                    if lo == hi {
+                        write!(f, "{}", lo)?;
+                    } else {
+                        write!(f, "{}-{}", lo, hi)?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for RemoteSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "hw{}:{}", self.hws, self.pat)
+    }
+}
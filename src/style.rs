@@ -0,0 +1,108 @@
+//! Colors and highlighting for terminal output.
+//!
+//! Painted text contains ANSI escape codes, so table cells holding it must
+//! be added with `with_ansi_cell` to keep their columns aligned.
+
+use super::config::ColorMode;
+use super::messages::SubmissionStatus;
+
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STDOUT_ENABLED: AtomicBool = AtomicBool::new(false);
+static STDERR_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The kinds of text that get colored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Style {
+    /// A deadline that has passed.
+    PastDue,
+    /// A deadline within the next day.
+    DueSoon,
+    /// The status of a submission.
+    Status(SubmissionStatus),
+    /// A grade in [0.0, 1.0].
+    Grade(f64),
+    /// The percentage of a quota that remains.
+    QuotaRemaining(f32),
+    /// Something awaiting the user’s action, such as an incoming partner
+    /// request.
+    Attention,
+    /// A non-fatal problem.
+    Warning,
+    /// A fatal error.
+    Error,
+}
+
+/// Decides whether to color stdout and stderr. In `Auto` mode, each stream
+/// is colored only if it’s a terminal and `$NO_COLOR` is unset.
+pub fn set_color_mode(mode: ColorMode) {
+    let (stdout, stderr) = match mode {
+        ColorMode::Always => (true, true),
+        ColorMode::Never  => (false, false),
+        ColorMode::Auto   => {
+            let no_color = env::var_os("NO_COLOR").is_some_and(|s| !s.is_empty());
+            (!no_color && atty::is(atty::Stream::Stdout),
+             !no_color && atty::is(atty::Stream::Stderr))
+        }
+    };
+
+    STDOUT_ENABLED.store(stdout, Ordering::Relaxed);
+    STDERR_ENABLED.store(stderr, Ordering::Relaxed);
+}
+
+impl Style {
+    fn escape(self) -> &'static str {
+        use self::SubmissionStatus::*;
+
+        const RED:          &str = "\x1b[31m";
+        const GREEN:        &str = "\x1b[32m";
+        const YELLOW:       &str = "\x1b[33m";
+        const BOLD_RED:     &str = "\x1b[1;31m";
+        const BOLD_GREEN:   &str = "\x1b[1;32m";
+        const BOLD_YELLOW:  &str = "\x1b[1;33m";
+        const BOLD_MAGENTA: &str = "\x1b[1;35m";
+        const BOLD_CYAN:    &str = "\x1b[1;36m";
+        const CYAN:         &str = "\x1b[36m";
+        const DIM:          &str = "\x1b[2m";
+
+        match self {
+            Style::PastDue               => RED,
+            Style::DueSoon               => BOLD_YELLOW,
+            Style::Status(Future)        => DIM,
+            Style::Status(Open)          => BOLD_GREEN,
+            Style::Status(SelfEval)      => BOLD_CYAN,
+            Style::Status(Extended)      => GREEN,
+            Style::Status(ExtendedEval)  => CYAN,
+            Style::Status(Closed)        => DIM,
+            Style::Grade(g) if g >= 0.9  => GREEN,
+            Style::Grade(g) if g >= 0.7  => YELLOW,
+            Style::Grade(_)              => RED,
+            Style::QuotaRemaining(p) if p < 10.0 => BOLD_RED,
+            Style::QuotaRemaining(p) if p < 25.0 => YELLOW,
+            Style::QuotaRemaining(_)     => GREEN,
+            Style::Attention             => BOLD_MAGENTA,
+            Style::Warning               => YELLOW,
+            Style::Error                 => BOLD_RED,
+        }
+    }
+}
+
+fn paint_if<D: Display>(enabled: bool, style: Style, text: D) -> String {
+    if enabled {
+        format!("{}{}\x1b[0m", style.escape(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Renders `text` in `style` if stdout is colored, or plainly otherwise.
+pub fn paint<D: Display>(style: Style, text: D) -> String {
+    paint_if(STDOUT_ENABLED.load(Ordering::Relaxed), style, text)
+}
+
+/// Like [`paint`], but for text bound for stderr.
+pub fn paint_err<D: Display>(style: Style, text: D) -> String {
+    paint_if(STDERR_ENABLED.load(Ordering::Relaxed), style, text)
+}
@@ -0,0 +1,183 @@
+//! The local trash, where `rm` keeps a copy of each file it deletes so that
+//! `restore` can put it back.
+//!
+//! Every trashed file gets its own directory under the trash directory,
+//! holding the file’s contents in `contents` and its metadata in
+//! `meta.json`. The metadata is written last, so a directory without it is
+//! an unfinished entry and is skipped. Since the trash may hold other
+//! users’ files, only its owner can read it, and entries are deleted for
+//! good once they are 30 days old.
+
+use super::cache::{create_private_dir, create_private_file, private_dir_builder};
+use super::errors::*;
+use super::messages::{DateTime, FileMeta};
+
+use serde_derive::{Serialize, Deserialize};
+use vlog::*;
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+const CONTENTS_NAME: &str = "contents";
+const META_NAME: &str     = "meta.json";
+const MAX_AGE_DAYS: i64   = 30;
+
+/// What we know about a trashed file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashMeta {
+    /// Whose submission the file was deleted from. Entries trashed before
+    /// owners were recorded have none.
+    #[serde(default)]
+    pub owner:          Option<String>,
+    pub hw:             usize,
+    pub deleted_time:   DateTime,
+    pub file:           FileMeta,
+}
+
+/// A trashed file.
+#[derive(Debug)]
+pub struct TrashEntry {
+    dir:        PathBuf,
+    pub meta:   TrashMeta,
+}
+
+#[derive(Debug)]
+pub struct Trash {
+    dir:        PathBuf,
+}
+
+impl TrashMeta {
+    /// Whether the file was deleted from `user`’s submission. An entry with
+    /// no recorded owner is taken to be the current user’s.
+    pub fn belongs_to(&self, user: &str) -> bool {
+        self.owner.as_ref().is_none_or(|owner| owner == user)
+    }
+}
+
+impl TrashEntry {
+    /// The file holding the trashed file’s contents.
+    pub fn contents_path(&self) -> PathBuf {
+        self.dir.join(CONTENTS_NAME)
+    }
+
+    /// Deletes the entry from the trash for good.
+    pub fn remove(self) -> Result<()> {
+        fs::remove_dir_all(&self.dir)
+            .chain_err(|| format!("Could not remove ‘{}’", self.dir.display()))?;
+        Ok(())
+    }
+}
+
+impl Trash {
+    pub fn new(dir: &Path) -> Self {
+        Trash { dir: dir.to_owned() }
+    }
+
+    /// Adds a file to the trash, reading its contents from `contents`, and
+    /// first deletes any entries that have grown too old.
+    pub fn put<R: Read>(&self, meta: TrashMeta, contents: &mut R) -> Result<TrashEntry> {
+        create_private_dir(&self.dir)
+            .chain_err(|| format!("Could not create trash ‘{}’", self.dir.display()))?;
+        self.prune();
+
+        let dir   = self.create_entry_dir(&meta)?;
+        let entry = TrashEntry { dir, meta };
+
+        let result = (|| -> Result<()> {
+            let mut file = create_private_file(&entry.contents_path())?;
+            io::copy(contents, &mut file)?;
+            file.sync_all()?;
+
+            let meta_file = create_private_file(&entry.dir.join(META_NAME))?;
+            serde_json::to_writer_pretty(meta_file, &entry.meta)?;
+            Ok(())
+        })();
+
+        match result {
+            Ok(())     => Ok(entry),
+            Err(error) => {
+                let _ = fs::remove_dir_all(&entry.dir);
+                Err(error).chain_err(|| format!("Could not move ‘hw{}:{}’ to the trash",
+                                                entry.meta.hw, entry.meta.file.name))
+            }
+        }
+    }
+
+    /// Every entry in the trash, oldest deletion first.
+    pub fn entries(&self) -> Result<Vec<TrashEntry>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(error)   => match error.kind() {
+                io::ErrorKind::NotFound => return Ok(Vec::new()),
+                _                       => Err(error)
+                    .chain_err(|| format!("Could not read trash ‘{}’", self.dir.display()))?,
+            }
+        };
+
+        let mut entries = Vec::new();
+
+        for dir_entry in read_dir {
+            let dir       = dir_entry?.path();
+            let meta_path = dir.join(META_NAME);
+
+            let meta = match fs::File::open(&meta_path) {
+                Ok(file)   => serde_json::from_reader(file)
+                    .chain_err(|| format!("Could not read ‘{}’", meta_path.display()))?,
+                Err(_)     => {
+                    ve3!("Skipping unfinished trash entry ‘{}’.", dir.display());
+                    continue;
+                }
+            };
+
+            entries.push(TrashEntry { dir, meta });
+        }
+
+        entries.sort_by(|a, b| a.meta.deleted_time.to_utc().cmp(&b.meta.deleted_time.to_utc())
+            .then(a.meta.hw.cmp(&b.meta.hw))
+            .then_with(|| a.meta.file.name.cmp(&b.meta.file.name)));
+
+        Ok(entries)
+    }
+
+    // Deletes the entries older than `MAX_AGE_DAYS`. The trash is only a
+    // safety net, so failures are logged rather than reported.
+    fn prune(&self) {
+        let entries = match self.entries() {
+            Ok(entries) => entries,
+            Err(error)  => {
+                ve3!("Could not prune trash: {}", error);
+                return;
+            }
+        };
+
+        let max_age = chrono::Duration::days(MAX_AGE_DAYS);
+
+        for entry in entries {
+            if -entry.meta.deleted_time.from_now() > max_age {
+                ve3!("Removing old trash entry ‘{}’.", entry.dir.display());
+                if let Err(error) = entry.remove() {
+                    ve3!("{}", error);
+                }
+            }
+        }
+    }
+
+    // Makes a fresh directory named after the file and the time.
+    fn create_entry_dir(&self, meta: &TrashMeta) -> Result<PathBuf> {
+        let stamp = meta.deleted_time.to_utc().format("%Y%m%dT%H%M%S");
+
+        for i in 0 .. {
+            let dir = self.dir.join(format!("hw{}-{}-{}", meta.hw, stamp, i));
+
+            match private_dir_builder().create(&dir) {
+                Ok(())                                                 => return Ok(dir),
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e)                                                 => Err(e)
+                    .chain_err(|| format!("Could not create ‘{}’", dir.display()))?,
+            }
+        }
+
+        unreachable!()
+    }
+}